@@ -25,7 +25,7 @@ use log::error;
 use app_dirs::{AppInfo, AppDataType, get_app_root};
 
 use crate::error;
-use super::{Locker, Interface, Timer, Auth, Saver};
+use super::{Locker, Interface, Timer, Auth, Saver, Power, Policy, Dialog, Keys};
 
 #[derive(Clone, Debug, Default)]
 pub struct Config {
@@ -36,6 +36,10 @@ pub struct Config {
 	timer:     Timer,
 	auth:      Auth,
 	saver:     Saver,
+	power:     Power,
+	policy:    Policy,
+	dialog:    Dialog,
+	keys:      Keys,
 }
 
 impl Config {
@@ -49,9 +53,13 @@ impl Config {
 	pub fn reset(&self) {
 		*self.locker.0.write().unwrap()    = Default::default();
 		*self.interface.0.write().unwrap() = Default::default();
-		*self.timer.0.write().unwrap()     = Default::default();
+		self.timer.reset();
 		*self.auth.0.write().unwrap()      = Default::default();
 		*self.saver.0.write().unwrap()     = Default::default();
+		*self.power.0.write().unwrap()     = Default::default();
+		*self.policy.0.write().unwrap()    = Default::default();
+		*self.dialog.0.write().unwrap()    = Default::default();
+		*self.keys.0.write().unwrap()      = Default::default();
 	}
 
 	pub fn reload<T: AsRef<Path>>(&self, path: Option<T>) -> error::Result<()> {
@@ -93,6 +101,10 @@ impl Config {
 		self.timer.load(&table);
 		self.auth.load(&table);
 		self.saver.load(&table);
+		self.power.load(&table);
+		self.policy.load(&table);
+		self.dialog.load(&table);
+		self.keys.load(&table);
 
 		Ok(())
 	}
@@ -116,4 +128,20 @@ impl Config {
 	pub fn saver(&self) -> Saver {
 		self.saver.clone()
 	}
+
+	pub fn power(&self) -> Power {
+		self.power.clone()
+	}
+
+	pub fn policy(&self) -> Policy {
+		self.policy.clone()
+	}
+
+	pub fn dialog(&self) -> Dialog {
+		self.dialog.clone()
+	}
+
+	pub fn keys(&self) -> Keys {
+		self.keys.clone()
+	}
 }
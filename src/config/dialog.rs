@@ -0,0 +1,134 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, RwLock};
+
+use toml;
+
+#[derive(Clone, Default, Debug)]
+pub struct Dialog(pub(super) Arc<RwLock<Data>>);
+
+#[derive(Debug)]
+pub(super) struct Data {
+	pub enabled:    bool,
+	pub font:       String,
+	pub foreground: u32,
+	pub background: u32,
+	pub position:   Position,
+	pub timeout:    u32,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Position {
+	Top,
+	Center,
+	Bottom,
+}
+
+impl Default for Data {
+	fn default() -> Data {
+		Data {
+			enabled:    false,
+			font:       "fixed".into(),
+			foreground: 0xffffff,
+			background: 0x000000,
+			position:   Position::Center,
+			timeout:    5,
+		}
+	}
+}
+
+impl Dialog {
+	pub fn load(&self, table: &toml::value::Table) {
+		if let Some(table) = table.get("locker").and_then(|v| v.as_table()) {
+			if let Some(table) = table.get("dialog").and_then(|v| v.as_table()) {
+				// The dialog is enabled by its section being there.
+				self.0.write().unwrap().enabled = table.get("enabled")
+					.and_then(|v| v.as_bool()).unwrap_or(true);
+
+				if let Some(value) = table.get("font").and_then(|v| v.as_str()) {
+					self.0.write().unwrap().font = value.into();
+				}
+
+				if let Some(value) = table.get("foreground").and_then(color) {
+					self.0.write().unwrap().foreground = value;
+				}
+
+				if let Some(value) = table.get("background").and_then(color) {
+					self.0.write().unwrap().background = value;
+				}
+
+				if let Some(value) = table.get("position").and_then(|v| v.as_str()) {
+					self.0.write().unwrap().position = match value {
+						"top" =>
+							Position::Top,
+
+						"bottom" =>
+							Position::Bottom,
+
+						_ =>
+							Position::Center
+					};
+				}
+
+				if let Some(value) = super::seconds(table.get("timeout")) {
+					self.0.write().unwrap().timeout = value;
+				}
+			}
+		}
+	}
+
+	/// Whether the locker draws its own password dialog.
+	pub fn enabled(&self) -> bool {
+		self.0.read().unwrap().enabled
+	}
+
+	/// The name of the core X font to use.
+	pub fn font(&self) -> String {
+		self.0.read().unwrap().font.clone()
+	}
+
+	/// The text color as `0xRRGGBB`.
+	pub fn foreground(&self) -> u32 {
+		self.0.read().unwrap().foreground
+	}
+
+	/// The background color as `0xRRGGBB`.
+	pub fn background(&self) -> u32 {
+		self.0.read().unwrap().background
+	}
+
+	pub fn position(&self) -> Position {
+		self.0.read().unwrap().position
+	}
+
+	/// How many seconds without input before hiding the dialog.
+	pub fn timeout(&self) -> u32 {
+		self.0.read().unwrap().timeout
+	}
+}
+
+/// Parse a color in the `"#RRGGBB"` form.
+fn color(value: &toml::Value) -> Option<u32> {
+	let value = value.as_str()?;
+
+	if value.len() != 7 || !value.starts_with('#') {
+		return None;
+	}
+
+	u32::from_str_radix(&value[1..], 16).ok()
+}
@@ -0,0 +1,118 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, RwLock};
+
+use toml;
+use log::warn;
+
+#[derive(Clone, Default, Debug)]
+pub struct Keys(pub(super) Arc<RwLock<Data>>);
+
+#[derive(Default, Debug)]
+pub(super) struct Data {
+	pub bindings: Vec<(Combo, Option<Action>)>,
+}
+
+/// A key combination, the key is either the name of a special key or a
+/// lowercase letter.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Combo {
+	pub control: bool,
+	pub key:     String,
+}
+
+/// What to do with the password being typed.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Action {
+	/// Delete the last character.
+	Delete,
+
+	/// Delete the last word.
+	DeleteWord,
+
+	/// Delete everything.
+	Clear,
+
+	/// Check the password.
+	Submit,
+}
+
+impl Keys {
+	pub fn load(&self, table: &toml::value::Table) {
+		if let Some(table) = table.get("locker").and_then(|v| v.as_table()) {
+			if let Some(table) = table.get("keys").and_then(|v| v.as_table()) {
+				for (key, value) in table {
+					let action = match value.as_str() {
+						Some("delete")      => Some(Action::Delete),
+						Some("delete-word") => Some(Action::DeleteWord),
+						Some("clear")       => Some(Action::Clear),
+						Some("submit")      => Some(Action::Submit),
+						Some("none")        => None,
+
+						_ => {
+							warn!("unknown action for key {}", key);
+							continue;
+						}
+					};
+
+					self.0.write().unwrap().bindings.push((combo(key), action));
+				}
+			}
+		}
+	}
+
+	/// Find the action bound to the key combination, the configured bindings
+	/// take precedence over the default ones.
+	pub fn action(&self, combo: &Combo) -> Option<Action> {
+		if let Some(&(_, action)) = self.0.read().unwrap().bindings.iter().find(|&&(ref c, _)| c == combo) {
+			return action;
+		}
+
+		match (combo.control, combo.key.as_ref()) {
+			(false, "BackSpace") | (true, "h") =>
+				Some(Action::Delete),
+
+			(true, "BackSpace") | (true, "w") =>
+				Some(Action::DeleteWord),
+
+			(false, "Escape") | (true, "u") =>
+				Some(Action::Clear),
+
+			(_, "Return") | (_, "KP_Enter") | (true, "j") | (true, "m") =>
+				Some(Action::Submit),
+
+			_ =>
+				None
+		}
+	}
+}
+
+/// Parse a key combination like `"ctrl+u"` or `"KP_Enter"`.
+fn combo(value: &str) -> Combo {
+	let (control, key) = if value.to_lowercase().starts_with("ctrl+") {
+		(true, &value[5..])
+	}
+	else {
+		(false, value)
+	};
+
+	Combo {
+		control: control,
+		key:     if key.chars().count() == 1 { key.to_lowercase() } else { key.into() },
+	}
+}
@@ -16,29 +16,63 @@
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::{Arc, RwLock};
+use std::collections::HashSet;
 
 use toml;
 
-use super::OnSuspend;
+use super::{OnSuspend, Dim, Background};
+use super::profile::{self, Profile};
 
 #[derive(Clone, Default, Debug)]
 pub struct Locker(pub(super) Arc<RwLock<Data>>);
 
 #[derive(Debug)]
 pub(super) struct Data {
-	pub display: Option<String>,
-	pub dpms:    bool,
+	pub display:   Option<String>,
+	pub dpms:      bool,
+	pub reconnect: bool,
 
 	pub on_suspend: OnSuspend,
+
+	pub dim_brightness: f32,
+	pub dim_fade:       u32,
+	pub dim_method:     Dim,
+
+	pub fade_in:  u32,
+	pub fade_out: u32,
+
+	pub background:      Option<Background>,
+	pub background_size: u32,
+
+	pub on_lock_failed:  Option<String>,
+	pub block_vt_switch: bool,
+
+	pub profiles: Vec<Profile>,
 }
 
 impl Default for Data {
 	fn default() -> Data {
 		Data {
-			display: None,
-			dpms:    true,
+			display:   None,
+			dpms:      true,
+			reconnect: false,
 
 			on_suspend: Default::default(),
+
+			dim_brightness: 0.5,
+			dim_fade:       1,
+			dim_method:     Default::default(),
+
+			fade_in:  0,
+			fade_out: 0,
+
+			background:      None,
+			background_size: 10,
+
+			on_lock_failed:  None,
+			block_vt_switch: false,
+
+			profiles: Vec::new(),
 		}
 	}
 }
@@ -54,22 +88,80 @@ impl Locker {
 				self.0.write().unwrap().dpms = false;
 			}
 
-			if let Some(value) = table.get("on-suspend").and_then(|v| v.as_str()) {
-				self.0.write().unwrap().on_suspend = match value {
-					"use-system-time" =>
-						OnSuspend::UseSystemTime,
+			if let Some(value) = table.get("reconnect").and_then(|v| v.as_bool()) {
+				self.0.write().unwrap().reconnect = value;
+			}
+
+			if let Some(value) = table.get("on-suspend").and_then(on_suspend) {
+				self.0.write().unwrap().on_suspend = value;
+			}
+
+			if let Some(value) = table.get("dim-brightness").and_then(brightness) {
+				self.0.write().unwrap().dim_brightness = value;
+			}
 
-					"lock" =>
-						OnSuspend::Lock,
+			if let Some(value) = super::seconds(table.get("dim-fade")) {
+				self.0.write().unwrap().dim_fade = value;
+			}
 
-					"activate" =>
-						OnSuspend::Activate,
+			if let Some(value) = table.get("dim-method").and_then(|v| v.as_str()) {
+				self.0.write().unwrap().dim_method = match value {
+					"backlight" =>
+						Dim::Backlight,
 
 					_ =>
 						Default::default()
 				};
 			}
+
+			if let Some(value) = super::seconds(table.get("fade-in")) {
+				self.0.write().unwrap().fade_in = value;
+			}
+
+			if let Some(value) = super::seconds(table.get("fade-out")) {
+				self.0.write().unwrap().fade_out = value;
+			}
+
+			if let Some(value) = table.get("background").and_then(|v| v.as_str()) {
+				self.0.write().unwrap().background = match value {
+					"blur" =>
+						Some(Background::Blur),
+
+					"pixelate" =>
+						Some(Background::Pixelate),
+
+					_ =>
+						None
+				};
+			}
+
+			if let Some(value) = table.get("background-size").and_then(|v| v.as_integer()) {
+				self.0.write().unwrap().background_size = value.max(0) as u32;
+			}
+
+			if let Some(value) = table.get("on-lock-failed").and_then(|v| v.as_str()) {
+				self.0.write().unwrap().on_lock_failed = Some(value.into());
+			}
+
+			if let Some(value) = table.get("block-vt-switch").and_then(|v| v.as_bool()) {
+				self.0.write().unwrap().block_vt_switch = value;
+			}
 		}
+
+		self.0.write().unwrap().profiles = profile::schedules(table, "locker");
+	}
+
+	/// Names of the active profiles.
+	pub fn profiles(&self) -> Vec<String> {
+		self.0.read().unwrap().profiles.iter()
+			.filter(|p| p.is_active(&HashSet::new()))
+			.map(|p| p.name.clone())
+			.collect()
+	}
+
+	/// Find an override from the active schedules.
+	fn get(&self, key: &str) -> Option<toml::Value> {
+		profile::find(&self.0.read().unwrap().profiles, &HashSet::new(), key)
 	}
 
 	pub fn display(&self) -> Option<String> {
@@ -80,7 +172,73 @@ impl Locker {
 		self.0.read().unwrap().dpms
 	}
 
+	pub fn reconnect(&self) -> bool {
+		self.0.read().unwrap().reconnect
+	}
+
 	pub fn on_suspend(&self) -> OnSuspend {
-		self.0.read().unwrap().on_suspend
+		let value = self.0.read().unwrap().on_suspend;
+		self.get("on-suspend").and_then(|v| on_suspend(&v)).unwrap_or(value)
+	}
+
+	pub fn dim_brightness(&self) -> f32 {
+		let value = self.0.read().unwrap().dim_brightness;
+		self.get("dim-brightness").and_then(|v| brightness(&v)).unwrap_or(value)
+	}
+
+	pub fn dim_fade(&self) -> u32 {
+		let value = self.0.read().unwrap().dim_fade;
+		self.get("dim-fade").and_then(|v| super::seconds(Some(&v))).unwrap_or(value)
+	}
+
+	pub fn dim_method(&self) -> Dim {
+		self.0.read().unwrap().dim_method
+	}
+
+	pub fn fade_in(&self) -> u32 {
+		let value = self.0.read().unwrap().fade_in;
+		self.get("fade-in").and_then(|v| super::seconds(Some(&v))).unwrap_or(value)
+	}
+
+	pub fn background(&self) -> Option<Background> {
+		self.0.read().unwrap().background
+	}
+
+	pub fn background_size(&self) -> u32 {
+		self.0.read().unwrap().background_size
+	}
+
+	pub fn on_lock_failed(&self) -> Option<String> {
+		self.0.read().unwrap().on_lock_failed.clone()
+	}
+
+	pub fn fade_out(&self) -> u32 {
+		let value = self.0.read().unwrap().fade_out;
+		self.get("fade-out").and_then(|v| super::seconds(Some(&v))).unwrap_or(value)
+	}
+
+	pub fn block_vt_switch(&self) -> bool {
+		self.0.read().unwrap().block_vt_switch
 	}
 }
+
+fn on_suspend(value: &toml::Value) -> Option<OnSuspend> {
+	Some(match value.as_str()? {
+		"use-system-time" =>
+			OnSuspend::UseSystemTime,
+
+		"lock" =>
+			OnSuspend::Lock,
+
+		"activate" =>
+			OnSuspend::Activate,
+
+		_ =>
+			Default::default()
+	})
+}
+
+fn brightness(value: &toml::Value) -> Option<f32> {
+	let value = value.as_float().or(value.as_integer().map(|v| v as f64))?;
+	Some(value.max(0.0).min(1.0) as f32)
+}
@@ -32,9 +32,23 @@ pub use self::auth::Auth;
 mod saver;
 pub use self::saver::Saver;
 
+mod power;
+pub use self::power::Power;
+
+mod policy;
+pub use self::policy::Policy;
+
+mod dialog;
+pub use self::dialog::{Dialog, Position};
+
+mod keys;
+pub use self::keys::{Keys, Combo, Action};
+
 mod config;
 pub use self::config::Config;
 
+mod profile;
+
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
 pub enum OnSuspend {
 	Ignore,
@@ -49,6 +63,24 @@ impl Default for OnSuspend {
 	}
 }
 
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Dim {
+	Gamma,
+	Backlight,
+}
+
+impl Default for Dim {
+	fn default() -> Dim {
+		Dim::Gamma
+	}
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Background {
+	Blur,
+	Pixelate,
+}
+
 fn seconds(value: Option<&toml::Value>) -> Option<u32> {
 	if value.is_none() {
 		return None;
@@ -0,0 +1,62 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, RwLock};
+
+use toml;
+
+#[derive(Clone, Default, Debug)]
+pub struct Policy(pub(super) Arc<RwLock<Data>>);
+
+#[derive(Debug)]
+pub(super) struct Data {
+	pub trusted: Vec<String>,
+}
+
+impl Default for Data {
+	fn default() -> Data {
+		Data {
+			trusted: Vec::new(),
+		}
+	}
+}
+
+impl Policy {
+	pub fn load(&self, table: &toml::value::Table) {
+		if let Some(table) = table.get("policy").and_then(|v| v.as_table()) {
+			if let Some(table) = table.get("network").and_then(|v| v.as_table()) {
+				if let Some(value) = table.get("trusted").and_then(|v| v.as_array()) {
+					self.0.write().unwrap().trusted = value.iter()
+						.filter(|v| v.as_str().is_some())
+						.map(|v| v.as_str().unwrap().into())
+						.collect();
+				}
+			}
+		}
+	}
+
+	/// List of trusted connection UUIDs or `SSID@BSSID` pairs.
+	pub fn trusted(&self) -> Vec<String> {
+		self.0.read().unwrap().trusted.clone()
+	}
+
+	/// Check if any of the given network identifiers is trusted.
+	pub fn is_trusted(&self, network: &[String]) -> bool {
+		let trusted = &self.0.read().unwrap().trusted;
+		network.iter().any(|id| trusted.contains(id))
+	}
+}
@@ -0,0 +1,99 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, RwLock};
+
+use toml;
+
+#[derive(Clone, Default, Debug)]
+pub struct Power(pub(super) Arc<RwLock<Data>>);
+
+#[derive(Debug)]
+pub(super) struct Data {
+	pub standby: Option<u32>,
+	pub suspend: Option<u32>,
+	pub off:     Option<u32>,
+
+	pub locked_standby: Option<u32>,
+	pub locked_suspend: Option<u32>,
+	pub locked_off:     Option<u32>,
+}
+
+impl Default for Data {
+	fn default() -> Data {
+		Data {
+			standby: None,
+			suspend: None,
+			off:     None,
+
+			locked_standby: None,
+			locked_suspend: None,
+			locked_off:     None,
+		}
+	}
+}
+
+impl Power {
+	pub fn load(&self, table: &toml::value::Table) {
+		if let Some(table) = table.get("power").and_then(|v| v.as_table()) {
+			if let Some(value) = super::seconds(table.get("standby")) {
+				self.0.write().unwrap().standby = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("suspend")) {
+				self.0.write().unwrap().suspend = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("off")) {
+				self.0.write().unwrap().off = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("locked-standby")) {
+				self.0.write().unwrap().locked_standby = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("locked-suspend")) {
+				self.0.write().unwrap().locked_suspend = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("locked-off")) {
+				self.0.write().unwrap().locked_off = Some(value);
+			}
+		}
+	}
+
+	/// Seconds of inactivity before going into standby.
+	pub fn standby(&self, locked: bool) -> Option<u32> {
+		let data = self.0.read().unwrap();
+
+		if locked { data.locked_standby.or(data.standby) } else { data.standby }
+	}
+
+	/// Seconds of inactivity before suspending.
+	pub fn suspend(&self, locked: bool) -> Option<u32> {
+		let data = self.0.read().unwrap();
+
+		if locked { data.locked_suspend.or(data.suspend) } else { data.suspend }
+	}
+
+	/// Seconds of inactivity before turning off.
+	pub fn off(&self, locked: bool) -> Option<u32> {
+		let data = self.0.read().unwrap();
+
+		if locked { data.locked_off.or(data.off) } else { data.off }
+	}
+}
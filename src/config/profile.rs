@@ -0,0 +1,327 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::mem;
+use std::ptr;
+
+use toml;
+use log::error;
+use libc;
+
+/// A set of values overriding the ones of a section while active.
+#[derive(Clone, Debug)]
+pub struct Profile {
+	pub name:  String,
+	pub when:  When,
+	pub table: toml::value::Table,
+}
+
+#[derive(Clone, Debug)]
+pub enum When {
+	/// Active while the named condition is set, like `battery`.
+	Condition(String),
+
+	/// Active while the wall-clock time is within the schedule.
+	Schedule(Schedule),
+}
+
+/// A range of minutes in the day on the given week days.
+///
+/// If `from` is after `to` the range wraps around midnight.
+#[derive(Copy, Clone, Debug)]
+pub struct Schedule {
+	pub days: u8,
+	pub from: u32,
+	pub to:   u32,
+}
+
+impl Schedule {
+	/// Parse a schedule from a `[[schedule]]` entry.
+	pub fn parse(table: &toml::value::Table) -> Option<Schedule> {
+		let days = match table.get("days") {
+			Some(value) => days(value)?,
+			None        => 0b111_1111,
+		};
+
+		let from = match table.get("from") {
+			Some(value) => minutes(value.as_str()?)?,
+			None        => 0,
+		};
+
+		let to = match table.get("to") {
+			Some(value) => minutes(value.as_str()?)?,
+			None        => 24 * 60,
+		};
+
+		Some(Schedule { days, from, to })
+	}
+
+	/// Check if the current local time is within the schedule.
+	pub fn is_active(&self) -> bool {
+		let (day, second) = now();
+
+		self.is_active_at(day, second / 60)
+	}
+
+	/// Check if the given week day and minute of the day are within the
+	/// schedule.
+	pub fn is_active_at(&self, day: u32, minute: u32) -> bool {
+		if self.from <= self.to {
+			self.days & (1 << day) != 0 && minute >= self.from && minute < self.to
+		}
+		else {
+			// The part after midnight belongs to the previous day.
+			let yesterday = (day + 6) % 7;
+
+			(self.days & (1 << day) != 0 && minute >= self.from) ||
+			(self.days & (1 << yesterday) != 0 && minute < self.to)
+		}
+	}
+
+	/// Seconds from the current local time until the schedule may start or
+	/// end.
+	pub fn until(&self) -> u64 {
+		self.until_at(now().1)
+	}
+
+	/// Seconds from the given second of the day until the schedule may start
+	/// or end.
+	pub fn until_at(&self, second: u32) -> u64 {
+		const DAY: u32 = 24 * 60 * 60;
+
+		[self.from, self.to].iter()
+			.map(|boundary| match (boundary * 60 + DAY - second) % DAY {
+				0       => DAY,
+				seconds => seconds,
+			} as u64)
+			.min().unwrap()
+	}
+}
+
+impl Profile {
+	/// Check if the profile is active with the given conditions.
+	pub fn is_active(&self, conditions: &HashSet<String>) -> bool {
+		match self.when {
+			When::Condition(ref name) =>
+				conditions.contains(name),
+
+			When::Schedule(ref schedule) =>
+				schedule.is_active(),
+		}
+	}
+}
+
+/// Find the value for the given key in the active profiles, later profiles
+/// take precedence.
+pub fn find(profiles: &[Profile], conditions: &HashSet<String>, key: &str) -> Option<toml::Value> {
+	profiles.iter().rev()
+		.filter(|p| p.is_active(conditions))
+		.find_map(|p| p.table.get(key))
+		.cloned()
+}
+
+/// Parse the profiles for the given section from the `[[schedule]]` entries.
+pub fn schedules(table: &toml::value::Table, section: &str) -> Vec<Profile> {
+	let entries = if let Some(value) = table.get("schedule").and_then(|v| v.as_array()) {
+		value
+	}
+	else {
+		return Vec::new();
+	};
+
+	entries.iter().enumerate().filter_map(|(index, entry)| {
+		let entry    = entry.as_table()?;
+		let values   = entry.get(section)?.as_table()?;
+		let schedule = if let Some(schedule) = Schedule::parse(entry) {
+			schedule
+		}
+		else {
+			error!("invalid schedule: {:?}", entry);
+			return None;
+		};
+
+		Some(Profile {
+			name:  entry.get("name").and_then(|v| v.as_str()).map(String::from)
+				.unwrap_or_else(|| format!("schedule.{}", index)),
+			when:  When::Schedule(schedule),
+			table: values.clone(),
+		})
+	}).collect()
+}
+
+/// Parse week days like `"mon-fri"`, `"sat,sun"` or `["mon", "wed"]` into a
+/// mask where bit 0 is sunday.
+fn days(value: &toml::Value) -> Option<u8> {
+	fn day(name: &str) -> Option<u8> {
+		Some(match &*name.trim().to_lowercase() {
+			"sun" | "sunday"    => 0,
+			"mon" | "monday"    => 1,
+			"tue" | "tuesday"   => 2,
+			"wed" | "wednesday" => 3,
+			"thu" | "thursday"  => 4,
+			"fri" | "friday"    => 5,
+			"sat" | "saturday"  => 6,
+			_                   => return None,
+		})
+	}
+
+	let parts = if let Some(value) = value.as_str() {
+		value.split(',').map(String::from).collect::<Vec<_>>()
+	}
+	else {
+		value.as_array()?.iter().map(|v| v.as_str().map(String::from)).collect::<Option<Vec<_>>>()?
+	};
+
+	let mut mask = 0;
+
+	for part in parts {
+		let mut range = part.splitn(2, '-');
+		let from      = day(range.next()?)?;
+		let to        = range.next().map(day).unwrap_or(Some(from))?;
+
+		// Ranges can wrap around the week, like `"sat-mon"`.
+		let mut current = from;
+		loop {
+			mask |= 1 << current;
+
+			if current == to {
+				break;
+			}
+
+			current = (current + 1) % 7;
+		}
+	}
+
+	Some(mask)
+}
+
+/// Parse a wall-clock time like `"09:30"` into minutes since midnight.
+fn minutes(value: &str) -> Option<u32> {
+	let mut parts = value.splitn(2, ':');
+	let hours     = parts.next()?.trim().parse::<u32>().ok()?;
+	let mins      = parts.next().map(|v| v.trim().parse::<u32>().ok()).unwrap_or(Some(0))?;
+
+	if hours > 24 || mins > 59 || (hours == 24 && mins != 0) {
+		return None;
+	}
+
+	Some(hours * 60 + mins)
+}
+
+/// Get the current local week day and second of the day.
+fn now() -> (u32, u32) {
+	unsafe {
+		let time   = libc::time(ptr::null_mut());
+		let mut tm = mem::zeroed::<libc::tm>();
+		libc::localtime_r(&time, &mut tm);
+
+		(tm.tm_wday as u32, (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as u32)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use toml;
+	use super::{Schedule, days, minutes};
+
+	fn schedule(source: &str) -> Option<Schedule> {
+		Schedule::parse(source.parse::<toml::Value>().unwrap().as_table().unwrap())
+	}
+
+	#[test]
+	fn day_ranges() {
+		assert_eq!(days(&"sun".into()), Some(0b000_0001));
+		assert_eq!(days(&"mon-fri".into()), Some(0b011_1110));
+		assert_eq!(days(&"Saturday, sunday".into()), Some(0b100_0001));
+		assert_eq!(days(&toml::Value::Array(vec!["mon".into(), "wed".into()])), Some(0b000_1010));
+
+		// Ranges wrap around the week.
+		assert_eq!(days(&"fri-mon".into()), Some(0b110_0011));
+	}
+
+	#[test]
+	fn times() {
+		assert_eq!(minutes("00:00"), Some(0));
+		assert_eq!(minutes("09:30"), Some(9 * 60 + 30));
+		assert_eq!(minutes("9"), Some(9 * 60));
+		assert_eq!(minutes("24:00"), Some(24 * 60));
+	}
+
+	#[test]
+	fn invalid() {
+		assert_eq!(days(&"funday".into()), None);
+		assert_eq!(days(&"mon-".into()), None);
+		assert_eq!(days(&toml::Value::Integer(1)), None);
+
+		assert_eq!(minutes("25:00"), None);
+		assert_eq!(minutes("12:60"), None);
+		assert_eq!(minutes("24:01"), None);
+		assert_eq!(minutes("noon"), None);
+
+		assert!(schedule(r#"days = "someday""#).is_none());
+		assert!(schedule(r#"from = "7:75""#).is_none());
+		assert!(schedule("to = 18").is_none());
+	}
+
+	#[test]
+	fn schedule_days() {
+		let schedule = schedule(r#"
+			days = "mon-fri"
+			from = "09:00"
+			to   = "18:00"
+		"#).unwrap();
+
+		assert!(schedule.is_active_at(1, 9 * 60));
+		assert!(schedule.is_active_at(5, 18 * 60 - 1));
+		assert!(!schedule.is_active_at(5, 18 * 60));
+		assert!(!schedule.is_active_at(3, 8 * 60 + 59));
+		assert!(!schedule.is_active_at(6, 12 * 60));
+	}
+
+	#[test]
+	fn midnight() {
+		let schedule = schedule(r#"
+			days = "fri"
+			from = "22:00"
+			to   = "06:00"
+		"#).unwrap();
+
+		assert!(schedule.is_active_at(5, 23 * 60));
+		assert!(!schedule.is_active_at(5, 21 * 60 + 59));
+
+		// The part after midnight belongs to the previous day.
+		assert!(schedule.is_active_at(6, 5 * 60 + 59));
+		assert!(!schedule.is_active_at(6, 6 * 60));
+		assert!(!schedule.is_active_at(6, 23 * 60));
+		assert!(!schedule.is_active_at(5, 5 * 60));
+	}
+
+	#[test]
+	fn boundaries() {
+		let schedule = schedule(r#"
+			from = "22:00"
+			to   = "06:00"
+		"#).unwrap();
+
+		assert_eq!(schedule.until_at(21 * 3600 + 59 * 60 + 30), 30);
+		assert_eq!(schedule.until_at(23 * 3600), 7 * 3600);
+
+		// Right at a boundary the next one is waited for.
+		assert_eq!(schedule.until_at(6 * 3600), 16 * 3600);
+	}
+}
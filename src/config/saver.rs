@@ -24,8 +24,10 @@ pub struct Saver(pub(super) Arc<RwLock<Data>>);
 
 #[derive(Debug)]
 pub(super) struct Data {
-	pub timeout:  u32,
-	pub throttle: bool,
+	pub timeout:    u32,
+	pub throttle:   bool,
+	pub on_battery: bool,
+	pub isolate:    bool,
 
 	pub using: Vec<String>,
 	pub table: toml::value::Table,
@@ -34,8 +36,10 @@ pub(super) struct Data {
 impl Default for Data {
 	fn default() -> Data {
 		Data {
-			timeout:  5,
-			throttle: false,
+			timeout:    5,
+			throttle:   false,
+			on_battery: true,
+			isolate:    false,
 
 			using: Default::default(),
 			table: Default::default(),
@@ -54,6 +58,14 @@ impl Saver {
 				self.0.write().unwrap().throttle = value;
 			}
 
+			if let Some(value) = table.get("throttle-on-battery").and_then(|v| v.as_bool()) {
+				self.0.write().unwrap().on_battery = value;
+			}
+
+			if let Some(value) = table.get("isolate").and_then(|v| v.as_bool()) {
+				self.0.write().unwrap().isolate = value;
+			}
+
 			if let Some(value) = table.get("use").and_then(|v| v.as_array()) {
 				self.0.write().unwrap().using = value.iter()
 					.filter(|v| v.as_str().is_some())
@@ -75,6 +87,16 @@ impl Saver {
 		self.0.read().unwrap().throttle
 	}
 
+	/// Whether throttling is enabled while on battery.
+	pub fn throttle_on_battery(&self) -> bool {
+		self.0.read().unwrap().on_battery
+	}
+
+	/// Whether savers draw offscreen instead of on the locker windows.
+	pub fn isolate(&self) -> bool {
+		self.0.read().unwrap().isolate
+	}
+
 	/// List of savers being used.
 	pub fn using(&self) -> Vec<String> {
 		self.0.read().unwrap().using.clone()
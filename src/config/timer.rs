@@ -16,8 +16,16 @@
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::{Arc, RwLock};
+use std::collections::HashSet;
+use std::mem;
 
 use toml;
+use log::warn;
+
+use super::profile::{self, Profile, When};
+
+/// The runtime conditions with a profile, in increasing precedence.
+const CONDITIONS: &[&str] = &["trusted", "battery"];
 
 #[derive(Clone, Default, Debug)]
 pub struct Timer(pub(super) Arc<RwLock<Data>>);
@@ -28,6 +36,14 @@ pub(super) struct Data {
 	pub timeout: u32,
 	pub lock:    Option<u32>,
 	pub blank:   Option<u32>,
+	pub warn:    Option<u32>,
+	pub dim:     Option<u32>,
+
+	pub locked_blank: Option<u32>,
+	pub lock_grace:   Option<u32>,
+
+	pub profiles:   Vec<Profile>,
+	pub conditions: HashSet<String>,
 }
 
 impl Default for Data {
@@ -37,6 +53,14 @@ impl Default for Data {
 			timeout: 360,
 			lock:    None,
 			blank:   None,
+			warn:    None,
+			dim:     None,
+
+			locked_blank: None,
+			lock_grace:   None,
+
+			profiles:   Vec::new(),
+			conditions: HashSet::new(),
 		}
 	}
 }
@@ -59,22 +83,195 @@ impl Timer {
 			if let Some(value) = super::seconds(table.get("blank")) {
 				self.0.write().unwrap().blank = Some(value);
 			}
+
+			if let Some(value) = super::seconds(table.get("warn")) {
+				self.0.write().unwrap().warn = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("dim")) {
+				self.0.write().unwrap().dim = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("locked-blank")) {
+				self.0.write().unwrap().locked_blank = Some(value);
+			}
+
+			if let Some(value) = super::seconds(table.get("lock-grace")) {
+				self.0.write().unwrap().lock_grace = Some(value);
+			}
+
+			for (name, value) in table {
+				if value.is_table() && !CONDITIONS.contains(&&**name) {
+					warn!("unknown timer profile: {}", name);
+				}
+			}
+		}
+
+		// Profiles are looked up from the last, so schedules come first and
+		// conditions override them.
+		self.0.write().unwrap().profiles.extend(profile::schedules(table, "timer"));
+
+		if let Some(table) = table.get("timer").and_then(|v| v.as_table()) {
+			for name in CONDITIONS {
+				if let Some(table) = table.get(*name).and_then(|v| v.as_table()) {
+					self.0.write().unwrap().profiles.push(Profile {
+						name:  (*name).into(),
+						when:  When::Condition((*name).into()),
+						table: table.clone(),
+					});
+				}
+			}
+		}
+	}
+
+	/// Reset the configuration, keeping the runtime conditions.
+	pub(super) fn reset(&self) {
+		let mut data = self.0.write().unwrap();
+		let conditions = mem::replace(&mut data.conditions, HashSet::new());
+
+		*data = Data { conditions, .. Default::default() };
+	}
+
+	/// Set or unset a runtime condition.
+	pub fn condition<S: Into<String>>(&self, name: S, value: bool) {
+		if value {
+			self.0.write().unwrap().conditions.insert(name.into());
+		}
+		else {
+			self.0.write().unwrap().conditions.remove(&name.into());
+		}
+	}
+
+	/// Names of the active profiles.
+	pub fn profiles(&self) -> Vec<String> {
+		let data = self.0.read().unwrap();
+
+		data.profiles.iter()
+			.filter(|p| p.is_active(&data.conditions))
+			.map(|p| p.name.clone())
+			.collect()
+	}
+
+	/// Seconds until the next schedule boundary, when the active profiles may
+	/// change.
+	pub fn boundary(&self) -> Option<u64> {
+		self.0.read().unwrap().profiles.iter()
+			.filter_map(|p| match p.when {
+				When::Schedule(ref schedule) => Some(schedule.until()),
+				When::Condition(..)          => None,
+			})
+			.min()
+	}
+
+	/// Find an override from the active profiles.
+	fn get(&self, key: &str) -> Option<toml::Value> {
+		let data = self.0.read().unwrap();
+		profile::find(&data.profiles, &data.conditions, key)
+	}
+
+	/// Get seconds from the active profiles or the given default.
+	fn seconds(&self, key: &str, default: u32) -> u32 {
+		self.get(key).and_then(|v| super::seconds(Some(&v))).unwrap_or(default)
+	}
+
+	/// Get optional seconds from the active profiles or the given default,
+	/// profiles can disable it with `false`.
+	fn maybe(&self, key: &str, default: Option<u32>) -> Option<u32> {
+		if let Some(value) = self.get(key) {
+			super::seconds(Some(&value))
+		}
+		else {
+			default
 		}
 	}
 
 	pub fn beat(&self) -> u32 {
-		self.0.read().unwrap().beat
+		let value = self.0.read().unwrap().beat;
+		self.seconds("beat", value)
 	}
 
 	pub fn timeout(&self) -> u32 {
-		self.0.read().unwrap().timeout
+		let value = self.0.read().unwrap().timeout;
+		self.seconds("timeout", value)
 	}
 
 	pub fn lock(&self) -> Option<u32> {
-		self.0.read().unwrap().lock
+		let value = self.0.read().unwrap().lock;
+		self.maybe("lock", value)
 	}
 
 	pub fn blank(&self) -> Option<u32> {
-		self.0.read().unwrap().blank
+		let value = self.0.read().unwrap().blank;
+		self.maybe("blank", value)
+	}
+
+	pub fn warn(&self) -> Option<u32> {
+		let value = self.0.read().unwrap().warn;
+		self.maybe("warn", value)
+	}
+
+	pub fn dim(&self) -> Option<u32> {
+		let value = self.0.read().unwrap().dim;
+		self.maybe("dim", value)
+	}
+
+	pub fn locked_blank(&self) -> Option<u32> {
+		let value = self.0.read().unwrap().locked_blank;
+		self.maybe("locked-blank", value)
+	}
+
+	pub fn lock_grace(&self) -> Option<u32> {
+		let value = self.0.read().unwrap().lock_grace;
+		self.maybe("lock-grace", value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use toml;
+	use super::Timer;
+
+	fn timer(source: &str) -> Timer {
+		let timer = Timer::default();
+		timer.load(source.parse::<toml::Value>().unwrap().as_table().unwrap());
+		timer
+	}
+
+	#[test]
+	fn precedence() {
+		let timer = timer(r#"
+			[timer]
+			timeout = 60
+
+			[timer.battery]
+			timeout = 10
+
+			[timer.trusted]
+			timeout = 20
+
+			[timer.typo]
+			timeout = 30
+
+			[[schedule]]
+			timer.timeout = 40
+		"#);
+
+		assert_eq!(timer.profiles(), vec!["schedule.0"]);
+		assert_eq!(timer.timeout(), 40);
+
+		timer.condition("trusted", true);
+		assert_eq!(timer.timeout(), 20);
+
+		timer.condition("battery", true);
+		assert_eq!(timer.timeout(), 10);
+		assert_eq!(timer.profiles(), vec!["schedule.0", "trusted", "battery"]);
+
+		// Unknown profiles never apply.
+		timer.condition("typo", true);
+		assert_eq!(timer.timeout(), 10);
+
+		timer.condition("battery", false);
+		timer.condition("trusted", false);
+		assert_eq!(timer.timeout(), 40);
 	}
 }
@@ -48,6 +48,7 @@ pub enum X {
 	MissingExtension,
 	Request(u8, u8),
 	Connection(xcb::ConnError),
+	Disconnected,
 }
 
 #[derive(Debug)]
@@ -214,6 +215,9 @@ impl error::Error for Error {
 
 				X::Connection(..) =>
 					"Connection to the X display failed.",
+
+				X::Disconnected =>
+					"Connection to the X display was lost.",
 			},
 
 			Error::DBus(ref err) => match *err {
@@ -16,9 +16,12 @@
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::path::Path;
+use std::collections::HashMap;
 use std::time::{SystemTime, Duration};
 use std::thread;
+use std::panic;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::ops::Deref;
 use channel::{self, Receiver, Sender, SendError};
 
@@ -26,7 +29,7 @@ use dbus::{
 	Message,
 	blocking::{
 		LocalConnection as Connection,
-		stdintf::org_freedesktop_dbus::RequestNameReply,
+		stdintf::org_freedesktop_dbus::{RequestNameReply, Properties as _, PropertiesPropertiesChanged},
 		BlockingSender as _,
 	},
 	channel::{Sender as _}
@@ -45,10 +48,15 @@ use crate::config;
 /// It listens for relevant system events:
 ///
 /// - `PrepareForSleep` from SystemD
+/// - `OnBattery` from UPower
+/// - `PrimaryConnection` from NetworkManager
 pub struct Interface {
 	receiver: Receiver<Request>,
 	sender:   Sender<Response>,
 	signals:  Sender<Signal>,
+	failures: Receiver<&'static str>,
+	running:  Arc<AtomicBool>,
+	threads:  Vec<thread::JoinHandle<()>>,
 }
 
 #[derive(Debug)]
@@ -109,6 +117,18 @@ pub enum Request {
 
 	/// The system is preparing for sleep or coming out of sleep.
 	PrepareForSleep(Option<SystemTime>),
+
+	/// The system switched between battery and AC power.
+	OnBattery(bool),
+
+	/// The primary network changed, with its name, UUID and SSID if any.
+	Network(Vec<String>),
+
+	/// Get the daemon status.
+	Status,
+
+	/// Get the safety level of the lock.
+	GetSafety,
 }
 
 #[derive(Debug)]
@@ -136,6 +156,12 @@ pub enum Response {
 
 	/// How many seconds the session has been idle.
 	SessionIdleTime(u64),
+
+	/// The daemon status as ordered key-value pairs.
+	Status(Vec<(String, String)>),
+
+	/// The safety level of the lock.
+	Safety(String),
 }
 
 #[derive(Debug)]
@@ -148,6 +174,16 @@ pub enum Signal {
 
 	/// An authentication request was initiated or completed.
 	AuthenticationRequest(bool),
+
+	/// The saver is going to start in the given seconds, or the warning was
+	/// cancelled.
+	IdleWarning(Option<u64>),
+
+	/// The screen was locked without grabbing the keyboard.
+	LockFailed(String),
+
+	/// The safety level of the lock changed.
+	SafetyChanged(String),
 }
 
 impl Interface {
@@ -277,12 +313,28 @@ impl Interface {
 		Ok(())
 	}
 
+	/// Send a status request.
+	pub fn status() -> error::Result<Vec<(String, String)>> {
+		Connection::new_session()?.send_with_reply_and_block(Message::new_method_call(
+			"meh.rust.ScreenSaver",
+			"/meh/rust/ScreenSaver",
+			"meh.rust.ScreenSaver",
+			"Status")?
+			, Duration::from_millis(5_000))?
+		.get1::<Vec<(String, String)>>()
+		.ok_or(dbus::Error::new_custom("status", "wrong response").into())
+	}
+
 	/// Spawn a DBus interface with the given configuration.
 	pub fn spawn(config: config::Interface) -> error::Result<Interface> {
 		let (sender,   i_receiver) = channel::unbounded();
 		let (i_sender, receiver)   = channel::unbounded();
 		let (s_sender, signals)    = channel::unbounded();
 		let (g_sender, g_receiver) = channel::unbounded::<error::Result<()>>();
+		let (f_sender, failures)   = channel::unbounded();
+
+		let running     = Arc::new(AtomicBool::new(true));
+		let mut threads = Vec::new();
 
 		macro_rules! dbus {
 			(connect system) => (
@@ -329,7 +381,25 @@ impl Interface {
 			);
 
 			(check) => (
-				g_receiver.recv().unwrap()
+				g_receiver.recv().unwrap_or_else(|_| Err(error::Error::Message("the session bus handler stopped".into())))
+			);
+
+			(request $sender:expr, $value:expr) => (
+				if $sender.send($value).is_err() {
+					return Err(dbus::tree::MethodErr::failed(&"the screen saver is stopping"));
+				}
+			);
+
+			(response $receiver:expr) => (
+				match $receiver.recv() {
+					Ok(value) => {
+						value
+					}
+
+					Err(_) => {
+						return Err(dbus::tree::MethodErr::failed(&"the screen saver is stopping"));
+					}
+				}
 			);
 
 			(try $body:expr) => (
@@ -355,9 +425,10 @@ impl Interface {
 
 		// System DBus handler.
 		{
-			let sender = sender.clone();
+			let sender  = sender.clone();
+			let running = running.clone();
 
-			thread::spawn(move || {
+			threads.push(supervise("system", f_sender.clone(), move || {
 				/// Inhibits system suspension temporarily.
 				fn inhibit(c: &Connection) -> Option<dbus::arg::OwnedFd> {
 					dbus!(try c.send_with_reply_and_block(dbus!(try Message::new_method_call(
@@ -372,13 +443,61 @@ impl Interface {
 						.get1()
 				}
 
-				let system = dbus!(connect system).unwrap();
+				/// Get the identifiers of the primary network connection.
+				fn network(c: &Connection) -> Vec<String> {
+					let manager = c.with_proxy("org.freedesktop.NetworkManager",
+						"/org/freedesktop/NetworkManager", Duration::from_millis(1_000));
+
+					let path = match manager.get::<dbus::Path<'static>>("org.freedesktop.NetworkManager", "PrimaryConnection") {
+						Ok(path) if &*path != "/" => path,
+						_                         => return Vec::new(),
+					};
+
+					let active = c.with_proxy("org.freedesktop.NetworkManager", path, Duration::from_millis(1_000));
+					let mut ids = Vec::new();
+
+					// The connection name is user controlled, only the UUID identifies
+					// the profile.
+					if let Ok(value) = active.get::<String>("org.freedesktop.NetworkManager.Connection.Active", "Uuid") {
+						ids.push(value);
+					}
+
+					// For wireless connections the specific object is the access point,
+					// the SSID alone can be spoofed so it's paired with the BSSID.
+					if let Ok(point) = active.get::<dbus::Path<'static>>("org.freedesktop.NetworkManager.Connection.Active", "SpecificObject") {
+						if &*point != "/" {
+							let point = c.with_proxy("org.freedesktop.NetworkManager", point, Duration::from_millis(1_000));
+
+							if let (Ok(ssid), Ok(bssid)) = (
+								point.get::<Vec<u8>>("org.freedesktop.NetworkManager.AccessPoint", "Ssid"),
+								point.get::<String>("org.freedesktop.NetworkManager.AccessPoint", "HwAddress"))
+							{
+								ids.push(format!("{}@{}", String::from_utf8_lossy(&ssid), bssid));
+							}
+						}
+					}
+
+					ids
+				}
+
+				let mut system = match dbus!(connect system) {
+					Ok(value) => {
+						value
+					}
+
+					Err(err) => {
+						error!("could not connect to the system bus: {:?}", err);
+						return;
+					}
+				};
 
 				// Delay the next suspension.
 				let mut inhibitor = inhibit(&system);
 
 				// Watch for PrepareForSleep events from SystemD.
-				dbus!(watch system, "path='/org/freedesktop/login1',interface='org.freedesktop.login1.Manager',member='PrepareForSleep'").unwrap();
+				if let Err(err) = dbus!(watch system, "path='/org/freedesktop/login1',interface='org.freedesktop.login1.Manager',member='PrepareForSleep'") {
+					error!("could not watch for sleep: {:?}", err);
+				}
 
 				#[derive(Debug)]
 				pub struct PrepareForSleep {
@@ -404,10 +523,10 @@ impl Interface {
 					const INTERFACE: &'static str = "org.freedesktop.login1.Manager";
 				}
 
-				system.with_proxy("org.freedesktop.login1.Manager", "/org/freedesktop/login1", Duration::from_micros(5_000))
-					.match_signal(|p: PrepareForSleep, _: &Connection, _: &Message| {
-						sender.send(Request::PrepareForSleep(
-							if p.arg0 { Some(SystemTime::now()) } else { None })).unwrap();
+				let watched = system.with_proxy("org.freedesktop.login1", "/org/freedesktop/login1", Duration::from_millis(5_000))
+					.match_signal(cloning!([sender] move |p: PrepareForSleep, c: &Connection, _: &Message| {
+						let _ = sender.send(Request::PrepareForSleep(
+							if p.arg0 { Some(SystemTime::now()) } else { None }));
 
 						// In case the system is suspending, unlock the suspension,
 						// otherwise delay the next.
@@ -415,19 +534,75 @@ impl Interface {
 							inhibitor.take();
 						}
 						else {
-							inhibitor = inhibit(&system);
+							inhibitor = inhibit(c);
 						}
 
 						true
-					});
-			});
+					}));
+
+				if let Err(err) = watched {
+					error!("could not watch for sleep: {:?}", err);
+				}
+
+				// Watch for power source changes from UPower.
+				{
+					let upower = system.with_proxy("org.freedesktop.UPower", "/org/freedesktop/UPower", Duration::from_millis(5_000));
+
+					if let Ok(value) = upower.get::<bool>("org.freedesktop.UPower", "OnBattery") {
+						let _ = sender.send(Request::OnBattery(value));
+					}
+
+					let watched = upower.match_signal(cloning!([sender] move |p: PropertiesPropertiesChanged, _: &Connection, _: &Message| {
+						if p.interface_name == "org.freedesktop.UPower" {
+							if let Some(value) = p.changed_properties.get("OnBattery").and_then(|v| v.0.as_i64()) {
+								let _ = sender.send(Request::OnBattery(value != 0));
+							}
+						}
+
+						true
+					}));
+
+					if let Err(err) = watched {
+						error!("could not watch UPower: {:?}", err);
+					}
+				}
+
+				// Watch for primary connection changes from NetworkManager.
+				{
+					let manager = system.with_proxy("org.freedesktop.NetworkManager", "/org/freedesktop/NetworkManager", Duration::from_millis(5_000));
+
+					let _ = sender.send(Request::Network(network(&system)));
+
+					let watched = manager.match_signal(cloning!([sender] move |p: PropertiesPropertiesChanged, c: &Connection, _: &Message| {
+						if p.interface_name == "org.freedesktop.NetworkManager" && p.changed_properties.contains_key("PrimaryConnection") {
+							let _ = sender.send(Request::Network(network(c)));
+						}
+
+						true
+					}));
+
+					if let Err(err) = watched {
+						error!("could not watch NetworkManager: {:?}", err);
+					}
+				}
+
+				// Keep going on errors, a bad message shouldn't stop the other
+				// signals.
+				while running.load(Ordering::Relaxed) {
+					if let Err(err) = system.process(Duration::from_millis(1_000)) {
+						error!("could not process system bus messages: {:?}", err);
+						thread::sleep(Duration::from_secs(1));
+					}
+				}
+			})?);
 		}
 
 		// Session DBus handler.
 		{
-			let sender = sender.clone();
+			let sender  = sender.clone();
+			let running = running.clone();
 
-			thread::spawn(move || {
+			threads.push(supervise("session", f_sender.clone(), move || {
 				let mut session = dbus!(connect session);
 				let f           = dbus::tree::Factory::new_sync::<()>();
 
@@ -435,12 +610,34 @@ impl Interface {
 				dbus!(register session, "meh.rust.ScreenSaver");
 				dbus!(ready);
 
+				/// Show or update the idle warning notification.
+				fn notify(c: &Connection, replaces: u32, seconds: u64) -> Option<u32> {
+					dbus!(try c.send_with_reply_and_block(dbus!(try Message::new_method_call(
+						"org.freedesktop.Notifications",
+						"/org/freedesktop/Notifications",
+						"org.freedesktop.Notifications",
+						"Notify"))
+							.append3("screenruster", replaces, "")
+							.append3("Screen saver", format!("Screen will lock in {}s", seconds), Vec::<String>::new())
+							.append2(HashMap::<String, dbus::arg::Variant<u8>>::new(), -1i32), Duration::from_millis(1_000)))
+						.get1()
+				}
+
 				// GNOME screensaver signals.
 				let active = Arc::new(f.signal("ActiveChanged", ()).sarg::<bool, _>("status"));
 				let idle   = Arc::new(f.signal("SessionIdleChanged", ()).sarg::<bool, _>("status"));
 				let begin  = Arc::new(f.signal("AuthenticationRequestBegin", ()));
 				let end    = Arc::new(f.signal("AuthenticationRequestEnd", ()));
 
+				// ScreenRuster signals.
+				let warning = Arc::new(f.signal("IdleWarning", ()).sarg::<bool, _>("status").sarg::<u64, _>("seconds"));
+				let failed  = Arc::new(f.signal("LockFailed", ()).sarg::<String, _>("reason"));
+				let safety  = Arc::new(f.signal("SafetyChanged", ()).sarg::<String, _>("level"));
+
+				// The current idle warning and its notification.
+				let mut warned       = false;
+				let mut notification = None::<u32>;
+
 				let tree = f.tree(())
 					// ScreenRuster interface.
 					.add(f.object_path("/meh/rust/ScreenSaver", ()).introspectable().add(f.interface("meh.rust.ScreenSaver", ())
@@ -449,9 +646,9 @@ impl Interface {
 								return Err(dbus::tree::MethodErr::failed(&"Reload is ignored"));
 							}
 
-							sender.send(Request::Reload(m.msg.get1())).unwrap();
+							dbus!(request sender, Request::Reload(m.msg.get1()));
 
-							if let Response::Reload(value) = receiver.recv().unwrap() {
+							if let Response::Reload(value) = dbus!(response receiver) {
 								Ok(vec![m.msg.method_return().append1(value)])
 							}
 							else {
@@ -465,12 +662,12 @@ impl Interface {
 							}
 
 							if let (Some(application), Some(reason)) = m.msg.get2() {
-								sender.send(Request::Suspend {
+								dbus!(request sender, Request::Suspend {
 									application: application,
 									reason:      reason
-								}).unwrap();
+								});
 
-								if let Response::Suspend(value) = receiver.recv().unwrap() {
+								if let Response::Suspend(value) = dbus!(response receiver) {
 									Ok(vec![m.msg.method_return().append1(value)])
 								}
 								else {
@@ -488,31 +685,57 @@ impl Interface {
 							}
 
 							if let Some(cookie) = m.msg.get1() {
-								sender.send(Request::Resume(cookie)).unwrap();
+								dbus!(request sender, Request::Resume(cookie));
 
 								Ok(vec![m.msg.method_return()])
 							}
 							else {
 								Err(dbus::tree::MethodErr::no_arg())
 							}
-						})).inarg::<u32, _>("cookie"))))
+						})).inarg::<u32, _>("cookie"))
+
+						.add_m(f.method("Status", (), cloning!([sender, receiver] move |m| {
+							dbus!(request sender, Request::Status);
+
+							if let Response::Status(value) = dbus!(response receiver) {
+								Ok(vec![m.msg.method_return().append1(value)])
+							}
+							else {
+								unreachable!();
+							}
+						})).outarg::<Vec<(String, String)>, _>("status"))
+
+						.add_m(f.method("GetSafety", (), cloning!([sender, receiver] move |m| {
+							dbus!(request sender, Request::GetSafety);
+
+							if let Response::Safety(value) = dbus!(response receiver) {
+								Ok(vec![m.msg.method_return().append1(value)])
+							}
+							else {
+								unreachable!();
+							}
+						})).outarg::<String, _>("level"))
+
+						.add_s(warning.clone())
+						.add_s(failed.clone())
+						.add_s(safety.clone())))
 
 					// GNOME screensaver interface.
 					.add(f.object_path("/org/gnome/ScreenSaver", ()).introspectable().add(f.interface("org.gnome.ScreenSaver", ())
 						.add_m(f.method("Lock", (), cloning!([sender] move |m| {
-							sender.send(Request::Lock).unwrap();
+							dbus!(request sender, Request::Lock);
 
 							Ok(vec![m.msg.method_return()])
 						})))
 
 						.add_m(f.method("Cycle", (), cloning!([sender] move |m| {
-							sender.send(Request::Cycle).unwrap();
+							dbus!(request sender, Request::Cycle);
 
 							Ok(vec![m.msg.method_return()])
 						})))
 
 						.add_m(f.method("SimulateUserActivity", (), cloning!([sender] move |m| {
-							sender.send(Request::SimulateUserActivity).unwrap();
+							dbus!(request sender, Request::SimulateUserActivity);
 
 							Ok(vec![m.msg.method_return()])
 						})))
@@ -523,12 +746,12 @@ impl Interface {
 							}
 
 							if let (Some(application), Some(reason)) = m.msg.get2() {
-								sender.send(Request::Inhibit {
+								dbus!(request sender, Request::Inhibit {
 									application: application,
 									reason:      reason
-								}).unwrap();
+								});
 
-								if let Response::Inhibit(value) = receiver.recv().unwrap() {
+								if let Response::Inhibit(value) = dbus!(response receiver) {
 									Ok(vec![m.msg.method_return().append1(value)])
 								}
 								else {
@@ -546,7 +769,7 @@ impl Interface {
 							}
 
 							if let Some(cookie) = m.msg.get1() {
-								sender.send(Request::UnInhibit(cookie)).unwrap();
+								dbus!(request sender, Request::UnInhibit(cookie));
 
 								Ok(vec![m.msg.method_return()])
 							}
@@ -561,12 +784,12 @@ impl Interface {
 							}
 
 							if let (Some(application), Some(reason)) = m.msg.get2() {
-								sender.send(Request::Throttle {
+								dbus!(request sender, Request::Throttle {
 									application: application,
 									reason:      reason
-								}).unwrap();
+								});
 
-								if let Response::Throttle(value) = receiver.recv().unwrap() {
+								if let Response::Throttle(value) = dbus!(response receiver) {
 									Ok(vec![m.msg.method_return().append1(value)])
 								}
 								else {
@@ -584,7 +807,7 @@ impl Interface {
 							}
 
 							if let Some(cookie) = m.msg.get1() {
-								sender.send(Request::UnThrottle(cookie)).unwrap();
+								dbus!(request sender, Request::UnThrottle(cookie));
 
 								Ok(vec![m.msg.method_return()])
 							}
@@ -595,7 +818,7 @@ impl Interface {
 
 						.add_m(f.method("SetActive", (), cloning!([sender] move |m| {
 							if let Some(value) = m.msg.get1() {
-								sender.send(Request::SetActive(value)).unwrap();
+								dbus!(request sender, Request::SetActive(value));
 
 								Ok(vec![m.msg.method_return()])
 							}
@@ -605,9 +828,9 @@ impl Interface {
 						})).inarg::<bool, _>("active"))
 
 						.add_m(f.method("GetActive", (), cloning!([sender, receiver] move |m| {
-							sender.send(Request::GetActive).unwrap();
+							dbus!(request sender, Request::GetActive);
 
-							if let Response::Active(value) = receiver.recv().unwrap() {
+							if let Response::Active(value) = dbus!(response receiver) {
 								Ok(vec![m.msg.method_return().append1(value)])
 							}
 							else {
@@ -616,9 +839,9 @@ impl Interface {
 						})).outarg::<bool, _>("active"))
 
 						.add_m(f.method("GetActiveTime", (), cloning!([sender, receiver] move |m| {
-							sender.send(Request::GetActiveTime).unwrap();
+							dbus!(request sender, Request::GetActiveTime);
 
-							if let Response::ActiveTime(time) = receiver.recv().unwrap() {
+							if let Response::ActiveTime(time) = dbus!(response receiver) {
 								Ok(vec![m.msg.method_return().append1(time)])
 							}
 							else {
@@ -627,9 +850,9 @@ impl Interface {
 						})).outarg::<u64, _>("time"))
 
 						.add_m(f.method("GetSessionIdle", (), cloning!([sender, receiver] move |m| {
-							sender.send(Request::GetSessionIdle).unwrap();
+							dbus!(request sender, Request::GetSessionIdle);
 
-							if let Response::SessionIdle(value) = receiver.recv().unwrap() {
+							if let Response::SessionIdle(value) = dbus!(response receiver) {
 								Ok(vec![m.msg.method_return().append1(value)])
 							}
 							else {
@@ -638,9 +861,9 @@ impl Interface {
 						})).outarg::<bool, _>("idle"))
 
 						.add_m(f.method("GetSessionIdleTime", (), cloning!([sender, receiver] move |m| {
-							sender.send(Request::GetSessionIdleTime).unwrap();
+							dbus!(request sender, Request::GetSessionIdleTime);
 
-							if let Response::SessionIdleTime(time) = receiver.recv().unwrap() {
+							if let Response::SessionIdleTime(time) = dbus!(response receiver) {
 								Ok(vec![m.msg.method_return().append1(time)])
 							}
 							else {
@@ -655,11 +878,46 @@ impl Interface {
 
 				tree.start_receive(&session);
 
-				loop {
-					session.process(Duration::from_millis(500));
+				while running.load(Ordering::Relaxed) {
+					if let Err(err) = session.process(Duration::from_millis(500)) {
+						error!("could not process session bus messages: {:?}", err);
+						thread::sleep(Duration::from_secs(1));
+					}
 
 					while let Ok(signal) = signals.try_recv() {
-						session.send(match signal {
+						// Keep the idle warning notification up to date.
+						match signal {
+							Signal::IdleWarning(Some(seconds)) => {
+								notification = notify(&session, notification.unwrap_or(0), seconds).or(notification);
+
+								if warned {
+									continue;
+								}
+
+								warned = true;
+							}
+
+							Signal::IdleWarning(None) => {
+								warned = false;
+
+								if let Some(id) = notification.take() {
+									if let Ok(message) = Message::new_method_call(
+										"org.freedesktop.Notifications",
+										"/org/freedesktop/Notifications",
+										"org.freedesktop.Notifications",
+										"CloseNotification")
+									{
+										if session.send(message.append1(id)).is_err() {
+											error!("could not close the idle warning notification");
+										}
+									}
+								}
+							}
+
+							_ => ()
+						}
+
+						let message = match signal {
 							Signal::Active(status) =>
 								active.msg(&"/meh/rust/ScreenSaver".into(), &"org.gnome.ScreenSaver".into()).append1(status),
 
@@ -671,21 +929,50 @@ impl Interface {
 
 							Signal::AuthenticationRequest(false) =>
 								end.msg(&"/meh/rust/ScreenSaver".into(), &"org.gnome.ScreenSaver".into()),
-						}).unwrap();
+
+							Signal::IdleWarning(seconds) =>
+								warning.msg(&"/meh/rust/ScreenSaver".into(), &"meh.rust.ScreenSaver".into())
+									.append2(seconds.is_some(), seconds.unwrap_or(0)),
+
+							Signal::LockFailed(reason) =>
+								failed.msg(&"/meh/rust/ScreenSaver".into(), &"meh.rust.ScreenSaver".into())
+									.append1(reason),
+
+							Signal::SafetyChanged(level) =>
+								safety.msg(&"/meh/rust/ScreenSaver".into(), &"meh.rust.ScreenSaver".into())
+									.append1(level),
+						};
+
+						if session.send(message).is_err() {
+							error!("could not send signal");
+						}
 					}
 				}
-			});
+			})?);
 		}
 
-		dbus!(check)?;
+		// Don't leave the system handler around if the session one failed.
+		if let Err(err) = dbus!(check) {
+			running.store(false, Ordering::Relaxed);
+			return Err(err);
+		}
 
 		Ok(Interface {
 			receiver: i_receiver,
 			sender:   i_sender,
 			signals:  s_sender,
+			failures: failures,
+			running:  running,
+			threads:  threads,
 		})
 	}
 
+	/// Names of the handlers that panicked, the interface has to be spawned
+	/// again when receiving one.
+	pub fn failures(&self) -> &Receiver<&'static str> {
+		&self.failures
+	}
+
 	pub fn response(&self, value: Response) -> Result<(), SendError<Response>> {
 		self.sender.send(value)
 	}
@@ -695,6 +982,20 @@ impl Interface {
 	}
 }
 
+impl Drop for Interface {
+	fn drop(&mut self) {
+		self.running.store(false, Ordering::Relaxed);
+
+		// Methods waiting for a response get an error instead.
+		self.sender = channel::unbounded().0;
+
+		// Wait for the handlers to release the bus names.
+		for thread in self.threads.drain(..) {
+			let _ = thread.join();
+		}
+	}
+}
+
 impl Deref for Interface {
 	type Target = Receiver<Request>;
 
@@ -702,3 +1003,14 @@ impl Deref for Interface {
 		&self.receiver
 	}
 }
+
+/// Spawn a handler thread, reporting its name if it panics.
+fn supervise<F>(name: &'static str, failures: Sender<&'static str>, body: F) -> error::Result<thread::JoinHandle<()>>
+	where F: FnOnce() + Send + 'static
+{
+	Ok(thread::Builder::new().name(format!("interface-{}", name)).spawn(move || {
+		if panic::catch_unwind(panic::AssertUnwindSafe(body)).is_err() {
+			let _ = failures.send(name);
+		}
+	})?)
+}
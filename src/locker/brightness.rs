@@ -0,0 +1,236 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Instant, Duration};
+
+use xcb;
+
+use crate::config;
+use super::Display;
+
+/// Screen brightness manager.
+///
+/// It changes the brightness of all the monitors either through the CRTC
+/// gamma ramps or the `Backlight` output property, keeping the original values
+/// around so they can be restored.
+pub struct Brightness {
+	display: Arc<Display>,
+	method:  config::Dim,
+	saved:   Option<Saved>,
+}
+
+enum Saved {
+	Gamma(Vec<Ramp>),
+	Backlight(Vec<Level>),
+}
+
+struct Ramp {
+	crtc:  u32,
+	red:   Vec<u16>,
+	green: Vec<u16>,
+	blue:  Vec<u16>,
+}
+
+struct Level {
+	output:   u32,
+	property: xcb::Atom,
+	value:    i32,
+	min:      i32,
+	max:      i32,
+}
+
+/// A brightness transition.
+#[derive(Copy, Clone, Debug)]
+pub struct Fade {
+	from:     f32,
+	to:       f32,
+	start:    Instant,
+	duration: Duration,
+}
+
+impl Fade {
+	/// Start a transition between the given levels.
+	pub fn new(from: f32, to: f32, duration: Duration) -> Fade {
+		Fade {
+			from:     from,
+			to:       to,
+			start:    Instant::now(),
+			duration: duration,
+		}
+	}
+
+	/// Get the current level.
+	pub fn level(&self) -> f32 {
+		if self.is_done() {
+			return self.to;
+		}
+
+		let progress = self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32();
+		self.from + (self.to - self.from) * progress
+	}
+
+	/// Check if the transition is over.
+	pub fn is_done(&self) -> bool {
+		self.start.elapsed() >= self.duration
+	}
+}
+
+impl Brightness {
+	/// Create a brightness manager using the given method.
+	pub fn new(display: Arc<Display>, method: config::Dim) -> Brightness {
+		Brightness {
+			display: display,
+			method:  method,
+			saved:   None,
+		}
+	}
+
+	/// Check if the brightness has been changed.
+	pub fn is_changed(&self) -> bool {
+		self.saved.is_some()
+	}
+
+	/// Change the brightness, `1.0` being the original brightness.
+	pub fn set(&mut self, level: f32) {
+		let level = level.max(0.0).min(1.0);
+
+		if self.saved.is_none() {
+			self.saved = Some(self.save());
+		}
+
+		match self.saved {
+			Some(Saved::Gamma(ref ramps)) => {
+				for ramp in ramps {
+					let scale = |values: &[u16]| values.iter()
+						.map(|&v| (v as f32 * level) as u16)
+						.collect::<Vec<u16>>();
+
+					xcb::randr::set_crtc_gamma(&self.display, ramp.crtc,
+						&scale(&ramp.red), &scale(&ramp.green), &scale(&ramp.blue));
+				}
+			}
+
+			Some(Saved::Backlight(ref levels)) => {
+				for backlight in levels {
+					let value = backlight.min + ((backlight.value - backlight.min) as f32 * level).round() as i32;
+
+					xcb::randr::change_output_property(&self.display, backlight.output, backlight.property,
+						xcb::ATOM_INTEGER, 32, xcb::PROP_MODE_REPLACE as u8, &[value.min(backlight.max)]);
+				}
+			}
+
+			None =>
+				unreachable!()
+		}
+
+		self.display.flush();
+	}
+
+	/// Restore the original brightness.
+	pub fn restore(&mut self) {
+		match self.saved.take() {
+			Some(Saved::Gamma(ramps)) => {
+				for ramp in ramps {
+					xcb::randr::set_crtc_gamma(&self.display, ramp.crtc,
+						&ramp.red, &ramp.green, &ramp.blue);
+				}
+			}
+
+			Some(Saved::Backlight(levels)) => {
+				for backlight in levels {
+					xcb::randr::change_output_property(&self.display, backlight.output, backlight.property,
+						xcb::ATOM_INTEGER, 32, xcb::PROP_MODE_REPLACE as u8, &[backlight.value]);
+				}
+			}
+
+			None =>
+				return
+		}
+
+		self.display.flush();
+	}
+
+	/// Save the current brightness, falling back to gamma ramps if no output
+	/// has a backlight.
+	fn save(&self) -> Saved {
+		if self.method == config::Dim::Backlight {
+			let levels = self.backlight();
+
+			if !levels.is_empty() {
+				return Saved::Backlight(levels);
+			}
+		}
+
+		Saved::Gamma(self.gamma())
+	}
+
+	/// Get the gamma ramps of all CRTCs.
+	fn gamma(&self) -> Vec<Ramp> {
+		self.display.crtcs().into_iter().filter_map(|crtc| {
+			let reply = xcb::randr::get_crtc_gamma(&self.display, crtc).get_reply().ok()?;
+
+			Some(Ramp {
+				crtc:  crtc,
+				red:   reply.red().to_vec(),
+				green: reply.green().to_vec(),
+				blue:  reply.blue().to_vec(),
+			})
+		}).collect()
+	}
+
+	/// Get the backlight levels of all outputs that have one.
+	fn backlight(&self) -> Vec<Level> {
+		// Newer drivers use `Backlight`, older ones `BACKLIGHT`.
+		let property = ["Backlight", "BACKLIGHT"].iter()
+			.filter_map(|name| xcb::intern_atom(&self.display, true, name).get_reply().ok())
+			.map(|reply| reply.atom())
+			.find(|&atom| atom != xcb::ATOM_NONE);
+
+		let property = if let Some(atom) = property {
+			atom
+		}
+		else {
+			return Vec::new();
+		};
+
+		self.display.outputs().into_iter().filter_map(|output| {
+			let range = xcb::randr::query_output_property(&self.display, output, property).get_reply().ok()?;
+
+			if !range.range() || range.valid_values().len() != 2 {
+				return None;
+			}
+
+			let reply = xcb::randr::get_output_property(&self.display, output, property,
+				xcb::ATOM_INTEGER, 0, 1, false, false).get_reply().ok()?;
+
+			if reply.type_() != xcb::ATOM_INTEGER || reply.format() != 32 || reply.num_items() != 1 {
+				return None;
+			}
+
+			let data = reply.data();
+
+			Some(Level {
+				output:   output,
+				property: property,
+				value:    i32::from_ne_bytes([data[0], data[1], data[2], data[3]]),
+				min:      range.valid_values()[0],
+				max:      range.valid_values()[1],
+			})
+		}).collect()
+	}
+}
@@ -0,0 +1,137 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use xcb;
+use log::warn;
+
+use crate::error;
+use crate::config::{self, Position};
+use super::Display;
+
+// Space between the border and the text.
+const PADDING: u32 = 10;
+
+// Minimum width of the dialog in characters.
+const COLUMNS: u32 = 32;
+
+/// A password dialog drawn by the locker on top of the saver.
+///
+/// It's a child of the locker window so it's always above whatever the saver
+/// draws.
+pub struct Dialog {
+	display: Arc<Display>,
+	config:  config::Dialog,
+	parent:  u32,
+	id:      u32,
+	gc:      u32,
+	font:    u32,
+
+	width:   u32,
+	ascent:  u32,
+	descent: u32,
+}
+
+impl Dialog {
+	/// Create a dialog for the given window.
+	pub fn create(display: Arc<Display>, parent: u32, config: config::Dialog) -> error::Result<Dialog> {
+		let font = display.generate_id();
+
+		if let Err(err) = xcb::open_font_checked(&display, font, &config.font()).request_check() {
+			warn!("could not open font {}: {:?}", config.font(), err);
+			xcb::open_font_checked(&display, font, "fixed").request_check()?;
+		}
+
+		let metrics = xcb::query_font(&display, font).get_reply()?;
+		let id      = display.generate_id();
+
+		xcb::create_window(&display, xcb::COPY_FROM_PARENT as u8, id, parent,
+			0, 0, 1, 1, 1, xcb::WINDOW_CLASS_INPUT_OUTPUT as u16, xcb::COPY_FROM_PARENT, &[
+				(xcb::CW_BACK_PIXEL, config.background()),
+				(xcb::CW_BORDER_PIXEL, config.foreground()),
+				(xcb::CW_BACKING_STORE, xcb::BACKING_STORE_WHEN_MAPPED),
+				(xcb::CW_OVERRIDE_REDIRECT, 1)]);
+
+		let gc = display.generate_id();
+		xcb::create_gc(&display, gc, id, &[
+			(xcb::GC_FOREGROUND, config.foreground()),
+			(xcb::GC_BACKGROUND, config.background()),
+			(xcb::GC_FONT, font)]);
+
+		Ok(Dialog {
+			display: display,
+			config:  config,
+			parent:  parent,
+			id:      id,
+			gc:      gc,
+			font:    font,
+
+			width:   metrics.max_bounds().character_width().max(1) as u32,
+			ascent:  metrics.font_ascent().max(0) as u32,
+			descent: metrics.font_descent().max(0) as u32,
+		})
+	}
+
+	/// Show the dialog with the given text.
+	pub fn show(&mut self, text: &str) {
+		let (parent_width, parent_height) = if let Ok(reply) = xcb::get_geometry(&self.display, self.parent).get_reply() {
+			(reply.width() as u32, reply.height() as u32)
+		}
+		else {
+			return;
+		};
+
+		let width  = COLUMNS.max(text.len() as u32) * self.width + PADDING * 2;
+		let height = self.ascent + self.descent + PADDING * 2;
+		let x      = parent_width.saturating_sub(width) / 2;
+		let y      = match self.config.position() {
+			Position::Top    => parent_height / 8,
+			Position::Center => parent_height.saturating_sub(height) / 2,
+			Position::Bottom => (parent_height - parent_height / 8).saturating_sub(height),
+		};
+
+		xcb::configure_window(&self.display, self.id, &[
+			(xcb::CONFIG_WINDOW_X as u16, x),
+			(xcb::CONFIG_WINDOW_Y as u16, y),
+			(xcb::CONFIG_WINDOW_WIDTH as u16, width),
+			(xcb::CONFIG_WINDOW_HEIGHT as u16, height),
+			(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)]);
+
+		xcb::map_window(&self.display, self.id);
+		xcb::clear_area(&self.display, false, self.id, 0, 0, 0, 0);
+		xcb::image_text_8(&self.display, self.id, self.gc,
+			PADDING as i16, (PADDING + self.ascent) as i16, text);
+
+		self.display.flush();
+	}
+
+	/// Hide the dialog.
+	pub fn hide(&mut self) {
+		xcb::unmap_window(&self.display, self.id);
+		self.display.flush();
+	}
+}
+
+impl Drop for Dialog {
+	fn drop(&mut self) {
+		xcb::free_gc(&self.display, self.gc);
+		xcb::close_font(&self.display, self.font);
+		xcb::destroy_window(&self.display, self.id);
+		self.display.flush();
+	}
+}
@@ -19,13 +19,16 @@ use std::sync::Arc;
 use std::ops::Deref;
 
 use xcb;
-use crate::{error, config, platform};
+use crate::{error, config, platform, timer};
 
 pub struct Display {
 	display: Arc<platform::Display>,
 
-	randr: bool,
-	dpms:  bool,
+	randr:     bool,
+	crtc:      bool,
+	dpms:      bool,
+	composite: bool,
+	damage:    bool,
 }
 
 unsafe impl Send for Display { }
@@ -37,13 +40,33 @@ impl Display {
 		let     display = platform::Display::open(config.display())?;
 		let     randr   = display.get_extension_data(xcb::randr::id());
 		let mut dpms    = display.get_extension_data(xcb::dpms::id());
+		let mut crtc    = false;
+		let mut overlay = false;
+		let mut damage  = false;
 
 		if randr.is_some() {
-			let version = xcb::randr::query_version(&display, 1, 1).get_reply()?;
+			let version = xcb::randr::query_version(&display, 1, 3).get_reply()?;
 
 			if version.major_version() < 1 || (version.major_version() >= 1 && version.minor_version() < 1) {
 				return Err(error::X::MissingExtension.into());
 			}
+
+			// CRTC gamma ramps and output properties need at least 1.3.
+			crtc = version.major_version() > 1 || version.minor_version() >= 3;
+		}
+
+		// The overlay window needs at least 0.3.
+		if display.get_extension_data(xcb::composite::id()).is_some() {
+			let version = xcb::composite::query_version(&display, 0, 4).get_reply()?;
+			overlay = version.major_version() > 0 || version.minor_version() >= 3;
+		}
+
+		// Damage reports through regions, which need XFixes 2.0.
+		if display.get_extension_data(xcb::damage::id()).is_some() && display.get_extension_data(xcb::xfixes::id()).is_some() {
+			let xfixes  = xcb::xfixes::query_version(&display, 5, 0).get_reply()?;
+			let version = xcb::damage::query_version(&display, 1, 1).get_reply()?;
+
+			damage = xfixes.major_version() >= 2 && version.major_version() >= 1;
 		}
 
 		if let Some(ext) = dpms.take() {
@@ -55,8 +78,11 @@ impl Display {
 		let display = Arc::new(Display {
 			display: display.clone(),
 
-			randr: randr.is_some(),
-			dpms:  dpms.is_some(),
+			randr:     randr.is_some(),
+			crtc:      crtc,
+			dpms:      dpms.is_some(),
+			composite: overlay,
+			damage:    damage,
 		});
 
 		display.sanitize();
@@ -74,6 +100,50 @@ impl Display {
 		}
 	}
 
+	/// Get the Composite extension data.
+	pub fn composite(&self) -> Option<xcb::QueryExtensionData> {
+		if self.composite {
+			Some(self.display.get_extension_data(xcb::composite::id()).unwrap())
+		}
+		else {
+			None
+		}
+	}
+
+	/// Get the Damage extension data.
+	pub fn damage(&self) -> Option<xcb::QueryExtensionData> {
+		if self.damage {
+			Some(self.display.get_extension_data(xcb::damage::id()).unwrap())
+		}
+		else {
+			None
+		}
+	}
+
+	/// Get the CRTCs of all the screens.
+	pub fn crtcs(&self) -> Vec<u32> {
+		if !self.crtc {
+			return Vec::new();
+		}
+
+		self.get_setup().roots()
+			.filter_map(|screen| xcb::randr::get_screen_resources_current(self, screen.root()).get_reply().ok())
+			.flat_map(|reply| reply.crtcs().to_vec())
+			.collect()
+	}
+
+	/// Get the outputs of all the screens.
+	pub fn outputs(&self) -> Vec<u32> {
+		if !self.crtc {
+			return Vec::new();
+		}
+
+		self.get_setup().roots()
+			.filter_map(|screen| xcb::randr::get_screen_resources_current(self, screen.root()).get_reply().ok())
+			.flat_map(|reply| reply.outputs().to_vec())
+			.collect()
+	}
+
 	/// Get the DPMS extension data.
 	pub fn dpms(&self) -> Option<xcb::QueryExtensionData> {
 		if self.dpms {
@@ -84,6 +154,40 @@ impl Display {
 		}
 	}
 
+	/// Get the composite overlay window of the given screen, if a compositing
+	/// manager is running on it.
+	///
+	/// The overlay window has to be released once done with it.
+	pub fn overlay(&self, screen: i32) -> Option<u32> {
+		if !self.composite {
+			return None;
+		}
+
+		let root      = self.get_setup().roots().nth(screen as usize)?.root();
+		let selection = xcb::intern_atom(self, false, &format!("_NET_WM_CM_S{}", screen))
+			.get_reply().ok()?.atom();
+
+		if xcb::get_selection_owner(self, selection).get_reply().ok()?.owner() == xcb::NONE {
+			return None;
+		}
+
+		Some(xcb::composite::get_overlay_window(self, root).get_reply().ok()?.overlay_win())
+	}
+
+	/// Get the composite overlay window of the given screen even without a
+	/// compositing manager, windows in it don't show up when querying the
+	/// window tree from the root.
+	///
+	/// The overlay window has to be released once done with it.
+	pub fn hidden(&self, screen: i32) -> Option<u32> {
+		if !self.composite {
+			return None;
+		}
+
+		let root = self.get_setup().roots().nth(screen as usize)?.root();
+		Some(xcb::composite::get_overlay_window(self, root).get_reply().ok()?.overlay_win())
+	}
+
 	/// Check if the monitor is powered on or not.
 	pub fn is_powered(&self) -> bool {
 		if !self.dpms {
@@ -127,6 +231,26 @@ impl Display {
 		self.flush();
 	}
 
+	/// Force the monitor into the given power saving stage.
+	pub fn stage(&self, stage: timer::Power) {
+		if !self.dpms {
+			return;
+		}
+
+		xcb::dpms::force_level(self, match stage {
+			timer::Power::Standby =>
+				xcb::dpms::DPMS_MODE_STANDBY,
+
+			timer::Power::Suspend =>
+				xcb::dpms::DPMS_MODE_SUSPEND,
+
+			timer::Power::Off =>
+				xcb::dpms::DPMS_MODE_OFF,
+		} as u16);
+
+		self.flush();
+	}
+
 	/// Sanitize the display from bad X11 things.
 	pub fn sanitize(&self) {
 		// Reset DPMS settings to usable.
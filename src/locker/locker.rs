@@ -17,19 +17,21 @@
 
 use std::collections::HashMap;
 use std::thread;
+use std::time::{Duration, Instant};
 use std::ops::Deref;
 use channel::{self, Receiver, Sender, SendError, select};
 
 use rand::{self, Rng};
+use log::warn;
 use xcb;
 use xkb::{self, key};
 
 use crate::error;
-use crate::config::Config;
+use crate::config::{self, Config, Combo, Action};
 use crate::timer;
 use crate::saver::{self, Saver, Safety, Password, Pointer};
-use super::{Display, Window};
-use crate::platform::{self, Keyboard};
+use super::{Display, Window, Brightness, Fade, Snapshot, Dialog};
+use crate::platform::{self, Keyboard, Text};
 use api;
 
 pub struct Locker {
@@ -37,12 +39,14 @@ pub struct Locker {
 	sender:   Sender<Request>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Request {
 	Sanitize,
 	Timeout { id: u64 },
 	Activity,
 	Power(bool),
+	Stage(timer::Power),
+	Dim(bool),
 	Throttle(bool),
 
 	Start,
@@ -51,12 +55,24 @@ pub enum Request {
 	Stop,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Response {
 	Timeout(timer::Timeout),
 	Activity,
 	Password(String),
 	Stopped,
+
+	/// The screen was locked without grabbing the keyboard.
+	LockFailed(String),
+
+	/// The grabbed inputs of the locked windows changed.
+	Safety {
+		keyboard: bool,
+		pointer:  bool,
+	},
+
+	/// The connection to the X server was lost.
+	Disconnected,
 }
 
 impl Locker {
@@ -67,19 +83,56 @@ impl Locker {
 		let mut savers   = HashMap::<u32, Saver>::new();
 		let mut checking = false;
 		let mut password = String::new();
+		let mut dimmer   = Brightness::new(display.clone(), config.locker().dim_method());
+		let mut dimming  = None::<Fade>;
+		let mut fader    = Brightness::new(display.clone(), config::Dim::Gamma);
+		let mut fading   = None::<Fade>;
+		let mut locked   = false;
+
+		// Whether the savers are being throttled.
+		let mut throttled = false;
+
+		// Whether the screen is fading out before the savers are started.
+		let mut activating = false;
+
+		// The inputs grabbed by all the locked windows.
+		let mut grabs = None::<(bool, bool)>;
+
+		// How many times the screen was unlocked, to drop the snapshots processed
+		// too late.
+		let mut generation = 0u64;
+
+		// The password dialogs and when they were last shown.
+		let mut dialogs  = HashMap::<u32, Dialog>::new();
+		let mut prompted = None::<Instant>;
+
+		// A previous locker may have left VT switching blocked.
+		if let Err(err) = keyboard.recover() {
+			warn!("could not restore VT switching: {:?}", err);
+		}
 
 		for screen in 0 .. display.screens() {
 			let window = Window::create(display.clone(), screen as i32)?;
 
-			display.observe(window.root()).unwrap();
+			display.observe(window.root())?;
 			windows.insert(window.id(), window);
 		}
 
 		let (sender,   i_receiver) = channel::unbounded();
 		let (i_sender, receiver)   = channel::unbounded();
 		let (s_sender, s_receiver) = channel::unbounded();
+		let (b_sender, b_receiver) = channel::unbounded::<(u64, u32, Snapshot)>();
 
 		thread::spawn(move || {
+			// Stop once the daemon is gone, there's nobody left to answer to.
+			macro_rules! respond {
+				($value:expr) => (
+					if sender.send($value).is_err() {
+						return;
+					}
+				);
+			}
+
 			macro_rules! window {
 				(list) => (
 					windows.values_mut()
@@ -89,6 +142,22 @@ impl Locker {
 					windows.get_mut(&$id)
 				);
 
+				// Isolated savers must never find the window, so their offscreen
+				// window is created before locking to keep it hidden.
+				(lock $window:expr) => ({
+					let window: &mut Window = &mut *$window;
+
+					if config.saver().isolate() && !config.saver().using().is_empty() {
+						if let Err(err) = window.offscreen() {
+							warn!("could not create offscreen window: {:?}", err);
+						}
+					}
+
+					if let Err(err) = window.lock() {
+						warn!("could not lock the window: {:?}", err);
+					}
+				});
+
 				($id:expr) => (
 					windows.get_mut(&$id).unwrap()
 				);
@@ -114,13 +183,13 @@ impl Locker {
 				(safety on $window:expr) => (
 					if let Some(saver) = saver!(? $window.id()) {
 						if $window.has_keyboard() && $window.has_pointer() {
-							saver.safety(Safety::High).unwrap();
+							saver.safety(Safety::High);
 						}
 						else if $window.has_keyboard() {
-							saver.safety(Safety::Medium).unwrap();
+							saver.safety(Safety::Medium);
 						}
 						else {
-							saver.safety(Safety::Low).unwrap();
+							saver.safety(Safety::Low);
 						}
 					}
 				);
@@ -134,13 +203,222 @@ impl Locker {
 				);
 			}
 
+			macro_rules! safety {
+				() => ({
+					let current = window!(list).filter(|w| w.is_locked()).fold(None, |grabs, w| {
+						let (keyboard, pointer) = grabs.unwrap_or((true, true));
+						Some((keyboard && w.has_keyboard(), pointer && w.has_pointer()))
+					});
+
+					if current != grabs {
+						grabs = current;
+
+						if let Some((keyboard, pointer)) = current {
+							respond!(Response::Safety { keyboard, pointer });
+						}
+					}
+				});
+			}
+
+			macro_rules! regrab {
+				() => ({
+					for window in window!(list) {
+						let keyboard = window.has_keyboard();
+						let pointer  = window.has_pointer();
+
+						window.sanitize();
+
+						if keyboard == window.has_keyboard() && pointer == window.has_pointer() {
+							continue;
+						}
+
+						// Clear the failure message now that the keyboard is grabbed.
+						if !keyboard && window.has_keyboard() && saver!(? window.id()).is_none() {
+							window.blank();
+						}
+
+						saver!(safety on window);
+					}
+
+					safety!();
+				});
+			}
+
+			macro_rules! compose {
+				// Drop any pending compose sequence.
+				(reset) => (
+					keyboard.reset();
+				);
+			}
+
+			macro_rules! dialog {
+				// Hide the dialogs.
+				(hide) => (
+					prompted = None;
+
+					for dialog in dialogs.values_mut() {
+						dialog.hide();
+					}
+				);
+
+				// Show the dialogs with the given text.
+				($text:expr) => (
+					if locked && config.dialog().enabled() {
+						prompted = Some(Instant::now());
+
+						for window in window!(list) {
+							if !window.is_locked() {
+								continue;
+							}
+
+							if !dialogs.contains_key(&window.id()) {
+								match Dialog::create(display.clone(), window.id(), config.dialog()) {
+									Ok(dialog) => {
+										dialogs.insert(window.id(), dialog);
+									}
+
+									Err(err) => {
+										warn!("could not create password dialog: {:?}", err);
+										continue;
+									}
+								}
+							}
+
+							dialogs.get_mut(&window.id()).unwrap().show($text);
+						}
+					}
+				);
+			}
+
+			macro_rules! start {
+				() => (
+					for window in window!(list) {
+						if !config.saver().using().is_empty() {
+							let name = &config.saver().using()[rand::thread_rng().gen_range(0, config.saver().using().len())];
+
+							// Isolated savers are only used if they can draw offscreen.
+							let target = if config.saver().isolate() {
+								window.offscreen().map_err(|err| warn!("could not create offscreen window: {:?}", err)).ok()
+							}
+							else {
+								Some(window.id())
+							};
+
+							if let Some((target, Ok(mut saver))) = target.map(|t| (t, Saver::spawn(&name))) {
+								let id = window.id();
+
+								respond!(Response::Timeout(timer::Timeout::Set {
+									id:      id as u64,
+									seconds: config.saver().timeout() as u64,
+								}));
+
+								let receiver = saver.take().unwrap();
+								let sender   = s_sender.clone();
+
+								thread::spawn(move || {
+									while let Ok(event) = receiver.recv() {
+										if sender.send((id, event)).is_err() {
+											break;
+										}
+									}
+								});
+
+								saver.config(config.saver().get(&name));
+								saver.target(display.name(), window.screen(), target as u64);
+
+								if throttled || config.saver().throttle() {
+									saver.throttle(true);
+								}
+
+								saver!(add id => saver);
+
+								continue;
+							}
+						}
+
+						window!(lock window);
+						window.blank();
+					}
+
+					safety!();
+				);
+			}
+
+			macro_rules! fade {
+				// Fade the screen out before activating.
+				(in $from:expr) => (
+					activating = true;
+					fader.set($from);
+					fading = Some(Fade::new($from, 0.0,
+						Duration::from_secs(config.locker().fade_in() as u64)));
+				);
+
+				// Abort the activation fade.
+				(abort) => (
+					if activating {
+						activating = false;
+						fading     = None;
+						fader.restore();
+
+						respond!(Response::Stopped);
+					}
+				);
+
+				// Show the windows once they're all locked.
+				(reveal) => (
+					if fader.is_changed() && fading.is_none() && !activating && windows.values().all(|w| w.is_locked()) {
+						fader.restore();
+					}
+				);
+
+				// Black out the screen before hiding a window.
+				(hide) => (
+					if config.locker().fade_out() > 0 && !fader.is_changed() && !display.crtcs().is_empty() {
+						fader.set(0.0);
+					}
+				);
+
+				// Fade the screen back in once all windows are hidden.
+				(out) => (
+					if fader.is_changed() && fading.is_none() && windows.values().all(|w| !w.is_locked()) {
+						fading = Some(Fade::new(0.0, 1.0,
+							Duration::from_secs(config.locker().fade_out() as u64)));
+					}
+				);
+			}
+
+			macro_rules! activity {
+				() => (
+					respond!(Response::Activity);
+
+					// Any input while fading in aborts the activation, unless locking.
+					if !locked {
+						fade!(abort);
+					}
+				);
+			}
+
 			let x = platform::display::sink(&display);
 
+			// Ticks for brightness transitions.
+			let ticker = channel::tick(Duration::from_millis(1_000 / 30));
+			let never  = channel::never();
+
+			// Ticks to grab the input again while the lock is unsafe.
+			let retry = channel::tick(Duration::from_secs(1));
+
 			loop {
 				select! {
 					// Handle control events.
 					recv(receiver) -> event => {
-						match event.unwrap() {
+						let event = if let Ok(event) = event {
+							event
+						}
+						else {
+							return;
+						};
+
+						match event {
 							Request::Timeout { id } => {
 								if let Some(saver) = saver!(? id as u32) {
 									saver.kill();
@@ -149,28 +427,18 @@ impl Locker {
 
 							Request::Sanitize => {
 								display.sanitize();
-
-								for window in window!(list) {
-									let keyboard = window.has_keyboard();
-									let pointer  = window.has_pointer();
-
-									window.sanitize();
-
-									if keyboard == window.has_keyboard() && pointer == window.has_pointer() {
-										continue;
-									}
-
-									saver!(safety on window);
-								}
+								regrab!();
 							}
 
 							Request::Activity => {
-								sender.send(Response::Activity).unwrap();
+								activity!();
 							}
 
 							Request::Throttle(value) => {
+								throttled = value;
+
 								for saver in saver!(list) {
-									saver.throttle(value).unwrap();
+									saver.throttle(value);
 								}
 							}
 
@@ -180,99 +448,260 @@ impl Locker {
 								}
 
 								for saver in saver!(list) {
-									saver.blank(!value).unwrap();
+									saver.blank(!value);
+								}
+
+								// While locked drop any partial password when blanking, and
+								// start the password entry over when unblanking unless the
+								// waking key already did.
+								if locked && (!value || password.is_empty()) {
+									password.clear();
+									checking = false;
+									compose!(reset);
+									dialog!(hide);
+
+									for saver in saver!(list) {
+										saver.password(Password::Reset);
+									}
 								}
 
 								display.power(value);
 							}
 
-							Request::Start => {
+							Request::Stage(stage) => {
 								for window in window!(list) {
-									if !config.saver().using().is_empty() {
-										let name = &config.saver().using()[rand::thread_rng().gen_range(0, config.saver().using().len())];
+									window.power(false);
+								}
 
-										if let Ok(mut saver) = Saver::spawn(&name) {
-											let id = window.id();
+								for saver in saver!(list) {
+									saver.blank(true);
+								}
 
-											sender.send(Response::Timeout(timer::Timeout::Set {
-												id:      id as u64,
-												seconds: config.saver().timeout() as u64,
-											})).unwrap();
+								display.stage(stage);
+							}
 
-											let receiver = saver.take().unwrap();
-											let sender   = s_sender.clone();
+							Request::Dim(value) => {
+								if value {
+									dimming = Some(Fade::new(1.0, config.locker().dim_brightness(),
+										Duration::from_secs(config.locker().dim_fade() as u64)));
+								}
+								else {
+									dimming = None;
+									dimmer.restore();
+								}
+							}
 
-											thread::spawn(move || {
-												while let Ok(event) = receiver.recv() {
-													sender.send((id, event)).unwrap();
-												}
-											});
+							Request::Start => {
+								// Capture the screen before covering it, the effect is applied
+								// in the background so locking isn't delayed by it.
+								if let Some(effect) = config.locker().background() {
+									let size = config.locker().background_size();
 
-											saver.config(config.saver().get(&name)).unwrap();
-											saver.target(display.name(), window.screen(), id as u64).unwrap();
+									for window in window!(list) {
+										if window.is_locked() {
+											continue;
+										}
 
-											if config.saver().throttle() {
-												saver.throttle(true).unwrap();
-											}
+										let (width, height) = window.dimensions();
 
-											saver!(add id => saver);
+										match Snapshot::capture(&display, window.root(), width, height) {
+											Ok(mut snapshot) => {
+												let id         = window.id();
+												let generation = generation;
+												let sender     = b_sender.clone();
 
-											continue;
+												thread::spawn(move || {
+													match effect {
+														config::Background::Blur =>
+															snapshot.blur(size),
+
+														config::Background::Pixelate =>
+															snapshot.pixelate(size),
+													}
+
+													let _ = sender.send((generation, id, snapshot));
+												});
+											}
+
+											Err(err) => {
+												warn!("could not capture the screen: {:?}", err);
+											}
 										}
 									}
+								}
+
+								// Hand the screen over to the saver, starting from the dimmed
+								// brightness if any.
+								let from = if dimmer.is_changed() {
+									dimming.map_or(config.locker().dim_brightness(), |f| f.level())
+								}
+								else {
+									1.0
+								};
 
-									window.lock().unwrap();
-									window.blank();
+								dimming = None;
+								dimmer.restore();
+
+								if config.locker().fade_in() > 0 && !display.crtcs().is_empty() {
+									fade!(in from);
+								}
+								else {
+									start!();
 								}
 							}
 
 							Request::Lock => {
+								locked = true;
+								compose!(reset);
+
+								if config.locker().block_vt_switch() {
+									if let Err(err) = keyboard.block(true) {
+										warn!("could not block VT switching: {:?}", err);
+									}
+								}
+
+								// Cover the screen and grab the input right away instead of
+								// waiting for the savers, they draw over it once ready.
+								for window in window!(list) {
+									if !window.is_locked() {
+										window!(lock window);
+										window.blank();
+									}
+								}
+
+								safety!();
+
 								for saver in saver!(list) {
-									saver.lock().unwrap();
+									saver.lock();
+								}
+
+								// Refuse to consider the screen locked without the keyboard, the
+								// savers know through the safety level, otherwise tell the user
+								// directly.
+								let mut failures = Vec::new();
+
+								for window in window!(list) {
+									if let Some(failure) = window.failure().map(String::from) {
+										if saver!(? window.id()).is_none() {
+											window.message(&format!("Screen is NOT locked safely: {}.", failure));
+										}
+
+										failures.push(failure);
+									}
+									else if !window.has_keyboard() {
+										failures.push("could not grab the keyboard".into());
+									}
+								}
+
+								if !failures.is_empty() {
+									respond!(Response::LockFailed(failures.join(", ")));
 								}
 							}
 
 							Request::Auth(state) => {
 								checking = false;
+								compose!(reset);
 
 								for saver in saver!(list) {
-									saver.password(if state { Password::Success } else { Password::Failure }).unwrap();
+									saver.password(if state { Password::Success } else { Password::Failure });
+								}
+
+								if state {
+									dialog!(hide);
+								}
+								else {
+									dialog!("Authentication failed.");
 								}
 							}
 
 							Request::Stop => {
+								locked = false;
+								compose!(reset);
+								fade!(abort);
+
+								if let Err(err) = keyboard.block(false) {
+									warn!("could not unblock VT switching: {:?}", err);
+								}
+
+								dialog!(hide);
+								dialogs.clear();
+
+								// Don't keep the snapshots around after unlocking.
+								generation += 1;
+
+								for window in window!(list) {
+									window.background(None);
+								}
+
 								for (&id, window) in &mut windows {
 									if let Some(saver) = saver!(? id) {
-										sender.send(Response::Timeout(timer::Timeout::Set {
+										respond!(Response::Timeout(timer::Timeout::Set {
 											id:      id as u64,
 											seconds: config.saver().timeout() as u64,
-										})).unwrap();
+										}));
 
-										saver.stop().unwrap();
+										saver.stop();
 									}
-									else {
-										window.unlock().unwrap();
+									else if window.is_locked() {
+										fade!(hide);
+										if let Err(err) = window.unlock() {
+											warn!("could not unlock the window: {:?}", err);
+										}
 									}
 								}
+
+								safety!();
+								fade!(out);
 							}
 						}
 					},
 
+					// Show the processed snapshots.
+					recv(b_receiver) -> background => {
+						let (current, id, snapshot) = if let Ok(value) = background {
+							value
+						}
+						else {
+							continue;
+						};
+
+						// The screen was unlocked or locked again in the meantime.
+						if current != generation || window!(? id).is_none() {
+							continue;
+						}
+
+						let window = window!(id);
+						window.background(Some(&snapshot));
+
+						// Savers draw over the window themselves.
+						if saver!(? id).is_none() {
+							window.blank();
+						}
+					},
+
 					// Handle saver events.
 					recv(s_receiver) -> event => {
-						let (id, event) = event.unwrap();
+						let (id, event) = if let Ok(value) = event {
+							value
+						}
+						else {
+							continue;
+						};
 
 						match event {
 							saver::Response::Forward(api::Response::Initialized) => {
-								saver!(id).start().unwrap();
+								saver!(id).start();
 							}
 
 							saver::Response::Forward(api::Response::Started) => {
 								if saver!(id).was_started() {
-									sender.send(Response::Timeout(timer::Timeout::Cancel { id: id as u64 })).unwrap();
+									respond!(Response::Timeout(timer::Timeout::Cancel { id: id as u64 }));
 
-									window!(id).lock().unwrap();
+									window!(lock window!(id));
 									saver!(safety id);
+									safety!();
+
+									fade!(reveal);
 								}
 								else {
 									saver!(id).kill();
@@ -284,21 +713,29 @@ impl Locker {
 									saver!(id).kill();
 								}
 								else {
-									sender.send(Response::Timeout(timer::Timeout::Cancel { id: id as u64 })).unwrap();
+									respond!(Response::Timeout(timer::Timeout::Cancel { id: id as u64 }));
 								}
 							}
 
 							saver::Response::Exit(..) => {
 								if saver!(id).was_stopped() {
-									window!(id).unlock().unwrap();
+									fade!(hide);
+									if let Err(err) = window!(id).unlock() {
+										warn!("could not unlock the window: {:?}", err);
+									}
+									safety!();
+									fade!(out);
 
 									if savers.len() == 1 {
-										sender.send(Response::Stopped).unwrap();
+										respond!(Response::Stopped);
 									}
 								}
 								else {
-									window!(id).lock().unwrap();
+									window!(lock window!(id));
 									window!(id).blank();
+									safety!();
+
+									fade!(reveal);
 								}
 
 								saver!(remove id);
@@ -306,9 +743,65 @@ impl Locker {
 						}
 					},
 
+					// Handle brightness transitions and isolated saver frames.
+					recv(if dimming.is_some() || fading.is_some() || prompted.is_some() || (config.saver().isolate() && display.damage().is_none() && !savers.is_empty()) { &ticker } else { &never }) -> _ => {
+						if let Some(fade) = dimming {
+							dimmer.set(fade.level());
+
+							if fade.is_done() {
+								dimming = None;
+							}
+						}
+
+						if let Some(fade) = fading {
+							fader.set(fade.level());
+
+							if fade.is_done() {
+								fading = None;
+
+								// The screen is black, start the savers and show the windows
+								// once they're ready, otherwise the screen is back to normal.
+								if activating {
+									activating = false;
+
+									start!();
+									fade!(reveal);
+								}
+								else {
+									fader.restore();
+								}
+							}
+						}
+
+						// Hide the password dialog after a while without input.
+						if !checking && prompted.map_or(false, |t| t.elapsed().as_secs() >= config.dialog().timeout() as u64) {
+							dialog!(hide);
+						}
+
+						// Show what the isolated savers drew, when changes aren't reported.
+						if display.damage().is_none() {
+							for window in window!(list) {
+								if window.is_locked() && saver!(? window.id()).is_some() {
+									window.present();
+								}
+							}
+						}
+					},
+
+					// Keep trying to grab the keyboard until it works.
+					recv(if locked && grabs.map_or(false, |(keyboard, _)| !keyboard) { &retry } else { &never }) -> _ => {
+						regrab!();
+					},
+
 					// Handle X events.
 					recv(x) -> event => {
-						let event = event.unwrap();
+						let event = if let Ok(event) = event {
+							event
+						}
+						else {
+							let _ = sender.send(Response::Disconnected);
+							return;
+						};
 
 						match event.response_type() {
 							// Handle screen changes.
@@ -320,12 +813,24 @@ impl Locker {
 										window.resize(event.width() as u32, event.height() as u32);
 
 										if let Some(saver) = saver!(? window.id()) {
-											saver.resize(event.width() as u32, event.height() as u32).unwrap();
+											saver.resize(event.width() as u32, event.height() as u32);
 										}
 									}
 								}
 							}
 
+							// Show what the isolated savers drew, the damage has to be
+							// collected even before locking to keep being notified.
+							e if display.damage().map_or(false, |d| e == d.first_event() + xcb::damage::NOTIFY) => {
+								let event = unsafe { xcb::cast_event::<xcb::damage::NotifyEvent>(&event) };
+
+								for window in window!(list) {
+									if window.owns_offscreen(event.drawable()) {
+										window.present();
+									}
+								}
+							}
+
 							// Handle keyboard events.
 							e if keyboard.owns_event(e) => {
 								keyboard.handle(&event);
@@ -336,7 +841,7 @@ impl Locker {
 							// Note we only act on key presses because `Xutf8LookupString`
 							// only generates strings from `KeyPress` events.
 							xcb::KEY_PRESS => {
-								sender.send(Response::Activity).unwrap();
+								activity!();
 
 								// Ignore keyboard input while checking authentication.
 								if checking {
@@ -345,68 +850,97 @@ impl Locker {
 
 								let event = unsafe { xcb::cast_event::<xcb::KeyPressEvent>(&event) };
 								if windows.values().find(|w| w.id() == event.event()).is_some() {
-									match keyboard.symbol(event.detail().into()) {
+									let combo  = combo(&keyboard, event.detail(), event.state());
+									let action = combo.and_then(|c| config.keys().action(&c));
+
+									// Any action ends a pending compose sequence.
+									if action.is_some() {
+										compose!(reset);
+									}
+
+									match action {
 										// Delete a character.
-										Some(key::BackSpace) => {
-											if !password.is_empty() {
-												password.pop();
+										Some(Action::Delete) => {
+											if password.pop().is_some() {
+												for saver in saver!(list) {
+													saver.password(Password::Delete);
+												}
+											}
+										}
+
+										// Delete the trailing spaces and the word before them.
+										Some(Action::DeleteWord) => {
+											let length = password.trim_end().len();
+											let length = password[.. length].trim_end_matches(|c: char| !c.is_whitespace()).len();
 
+											for _ in password.drain(length ..) {
 												for saver in saver!(list) {
-													saver.password(Password::Delete).unwrap();
+													saver.password(Password::Delete);
 												}
 											}
 										}
 
 										// Clear the password.
-										Some(key::Escape) => {
+										Some(Action::Clear) => {
 											if !password.is_empty() {
 												password.clear();
 
 												for saver in saver!(list) {
-													saver.password(Password::Reset).unwrap();
+													saver.password(Password::Reset);
 												}
 											}
 										}
 
 										// Check authentication.
-										Some(key::Return) => {
+										Some(Action::Submit) => {
 											for saver in saver!(list) {
-												saver.password(Password::Check).unwrap();
+												saver.password(Password::Check);
 											}
 
-											sender.send(Response::Password(password)).unwrap();
+											respond!(Response::Password(password));
 
 											checking = true;
 											password = String::new();
 										}
 
-										_ => {
+										None => {
 											// Limit the maximum password length so keeping a button
 											// pressed is not going to OOM us in the extremely long
 											// run.
-											if password.len() <= 255 {
-												if let Some(string) = keyboard.string(event.detail().into()) {
-													for ch in string.chars() {
+											match keyboard.text(event.detail()) {
+												Text::String(ref string) if password.len() <= 255 => {
+													// Unbound control characters aren't part of the password.
+													for ch in string.chars().filter(|c| !c.is_control()) {
 														password.push(ch);
 
 														for saver in saver!(list) {
-															saver.password(Password::Insert).unwrap();
+															saver.password(Password::Insert);
 														}
 													}
 												}
+
+												// Compose sequences only insert once they're finished.
+												_ => ()
 											}
 										}
 									}
+
+									if checking {
+										dialog!("Checking...");
+									}
+									else {
+										dialog!(&format!("Password: {}", "*".repeat(password.chars().count().min(24))));
+									}
 								}
 							}
 
 							xcb::KEY_RELEASE => {
-								sender.send(Response::Activity).unwrap();
+								activity!();
 							}
 
 							// Handle mouse button presses.
 							xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
-								sender.send(Response::Activity).unwrap();
+								activity!();
 
 								let event = unsafe { xcb::cast_event::<xcb::ButtonPressEvent>(&event) };
 								if let Some(window) = windows.values().find(|w| w.id() == event.event()) {
@@ -417,14 +951,14 @@ impl Locker {
 
 											button: event.detail(),
 											press:  event.response_type() == xcb::BUTTON_PRESS,
-										}).unwrap()
+										});
 									}
 								}
 							}
 
 							// Handle mouse motion.
 							xcb::MOTION_NOTIFY => {
-								sender.send(Response::Activity).unwrap();
+								activity!();
 
 								let event = unsafe { xcb::cast_event::<xcb::MotionNotifyEvent>(&event) };
 								if let Some(window) = windows.values().find(|w| w.id() == event.event()) {
@@ -432,7 +966,7 @@ impl Locker {
 										saver.pointer(Pointer::Move {
 											x: event.event_x() as i32,
 											y: event.event_y() as i32,
-										}).unwrap();
+										});
 									}
 								}
 							}
@@ -440,7 +974,8 @@ impl Locker {
 							// On window changes, try to observe the window.
 							xcb::MAP_NOTIFY | xcb::CONFIGURE_NOTIFY => {
 								let event = unsafe { xcb::cast_event::<xcb::MapNotifyEvent>(&event) };
-								display.observe(event.window()).unwrap();
+								// The window may be gone already.
+								let _ = display.observe(event.window());
 							}
 
 							_ => ()
@@ -456,40 +991,9 @@ impl Locker {
 		})
 	}
 
-	pub fn sanitize(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Sanitize)
-	}
-
-	pub fn timeout(&self, id: u64) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Timeout { id: id })
-	}
-
-	pub fn start(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Start)
-	}
-
-	pub fn lock(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Lock)
-	}
-
-	pub fn auth(&self, value: bool) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Auth(value))
-	}
-
-	pub fn stop(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Stop)
-	}
-
-	pub fn power(&self, value: bool) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Power(value))
-	}
-
-	pub fn activity(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Activity)
-	}
-
-	pub fn throttle(&self, value: bool) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Throttle(value))
+	/// Send a request to the locker.
+	pub fn send(&self, request: Request) -> Result<(), SendError<Request>> {
+		self.sender.send(request)
 	}
 }
 
@@ -500,3 +1004,28 @@ impl Deref for Locker {
 		&self.receiver
 	}
 }
+
+/// Find the key combination for a key press, letters are only considered
+/// together with Control.
+fn combo(keyboard: &Keyboard, code: u8, state: u16) -> Option<Combo> {
+	let control = state as u32 & xcb::MOD_MASK_CONTROL != 0;
+	let key     = match keyboard.symbol(code) {
+		Some(key::BackSpace) => "BackSpace".into(),
+		Some(key::Return)    => "Return".into(),
+		Some(key::KP_Enter)  => "KP_Enter".into(),
+		Some(key::Escape)    => "Escape".into(),
+
+		// With Control letters turn into control characters.
+		_ if control => {
+			let string = keyboard.string(code)?;
+			let ch     = string.chars().next().filter(|&c| c >= '\u{1}' && c <= '\u{1a}')?;
+
+			((ch as u8 | 0x60) as char).to_string()
+		}
+
+		_ =>
+			return None
+	};
+
+	Some(Combo { control, key })
+}
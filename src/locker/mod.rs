@@ -23,3 +23,12 @@ pub use self::display::Display;
 
 mod window;
 pub use self::window::Window;
+
+mod brightness;
+pub use self::brightness::{Brightness, Fade};
+
+mod snapshot;
+pub use self::snapshot::Snapshot;
+
+mod dialog;
+pub use self::dialog::Dialog;
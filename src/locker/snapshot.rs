@@ -0,0 +1,160 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use xcb;
+
+use crate::error;
+
+/// A capture of the screen contents as 32 bits per pixel BGRX rows.
+pub struct Snapshot {
+	width:  u32,
+	height: u32,
+	data:   Vec<u8>,
+}
+
+impl Snapshot {
+	/// Capture the contents of the given window.
+	pub fn capture(display: &xcb::Connection, window: u32, width: u32, height: u32) -> error::Result<Snapshot> {
+		let reply = xcb::get_image(display, xcb::IMAGE_FORMAT_Z_PIXMAP as u8, window,
+			0, 0, width as u16, height as u16, !0).get_reply()?;
+
+		if (reply.depth() != 24 && reply.depth() != 32) || reply.data().len() != (width * height * 4) as usize {
+			return Err(format!("unsupported image format with depth {}", reply.depth()).into());
+		}
+
+		Ok(Snapshot {
+			width:  width,
+			height: height,
+			data:   reply.data().to_vec(),
+		})
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	pub fn data(&self) -> &[u8] {
+		&self.data
+	}
+
+	/// Blur the image, approximating a gaussian blur with three box blurs of
+	/// the given radius.
+	pub fn blur(&mut self, radius: u32) {
+		if radius == 0 {
+			return;
+		}
+
+		let mut buffer = vec![0; self.data.len()];
+
+		for _ in 0 .. 3 {
+			blur(&self.data, &mut buffer, self.width, self.height, radius, true);
+			blur(&buffer, &mut self.data, self.width, self.height, radius, false);
+		}
+	}
+
+	/// Pixelate the image with blocks of the given size.
+	pub fn pixelate(&mut self, size: u32) {
+		if size <= 1 {
+			return;
+		}
+
+		let width  = self.width as usize;
+		let height = self.height as usize;
+		let size   = size as usize;
+
+		for top in (0 .. height).step_by(size) {
+			for left in (0 .. width).step_by(size) {
+				let bottom = (top + size).min(height);
+				let right  = (left + size).min(width);
+				let count  = ((bottom - top) * (right - left)) as u32;
+
+				let mut sum = [0u32; 4];
+
+				for y in top .. bottom {
+					for x in left .. right {
+						let offset = (y * width + x) * 4;
+
+						for c in 0 .. 4 {
+							sum[c] += self.data[offset + c] as u32;
+						}
+					}
+				}
+
+				for y in top .. bottom {
+					for x in left .. right {
+						let offset = (y * width + x) * 4;
+
+						for c in 0 .. 4 {
+							self.data[offset + c] = (sum[c] / count) as u8;
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Box blur the pixels of `source` into `target`, horizontally or vertically.
+fn blur(source: &[u8], target: &mut [u8], width: u32, height: u32, radius: u32, horizontal: bool) {
+	let (lines, length) = if horizontal { (height, width) } else { (width, height) };
+	let radius          = radius as i64;
+	let size            = (radius * 2 + 1) as u32;
+
+	for line in 0 .. lines as usize {
+		// The offset of a pixel in the line, the edges are repeated.
+		let offset = |index: i64| {
+			let index = index.max(0).min(length as i64 - 1) as usize;
+
+			if horizontal {
+				(line * width as usize + index) * 4
+			}
+			else {
+				(index * width as usize + line) * 4
+			}
+		};
+
+		let mut sum = [0u32; 4];
+
+		for index in -radius ..= radius {
+			let pixel = offset(index);
+
+			for c in 0 .. 4 {
+				sum[c] += source[pixel + c] as u32;
+			}
+		}
+
+		for index in 0 .. length as i64 {
+			let pixel = offset(index);
+
+			for c in 0 .. 4 {
+				target[pixel + c] = (sum[c] / size) as u8;
+			}
+
+			let next     = offset(index + radius + 1);
+			let previous = offset(index - radius);
+
+			for c in 0 .. 4 {
+				sum[c] += source[next + c] as u32;
+				sum[c] -= source[previous + c] as u32;
+			}
+		}
+	}
+}
@@ -24,7 +24,7 @@ use xcb;
 use log::warn;
 
 use crate::error;
-use super::Display;
+use super::{Display, Snapshot};
 use crate::platform;
 
 pub struct Window {
@@ -32,10 +32,27 @@ pub struct Window {
 	window:  platform::Window,
 	gc:      u32,
 	cursor:  u32,
+	overlay: Option<u32>,
+
+	background: Option<u32>,
+	offscreen:  Option<Offscreen>,
 
 	locked:   bool,
 	keyboard: bool,
 	pointer:  bool,
+	failure:  Option<String>,
+}
+
+/// The window isolated savers draw into, and the pixmap with its contents.
+#[derive(Copy, Clone, Debug)]
+struct Offscreen {
+	id:     u32,
+	pixmap: u32,
+	width:  u32,
+	height: u32,
+
+	/// The damage tracking its changes and the region to collect them in.
+	damage: Option<(u32, u32)>,
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -70,7 +87,9 @@ impl Window {
 			xcb::ATOM_CARDINAL, 32, &[index]);
 
 		let gc = display.generate_id();
-		xcb::create_gc(&display, gc, window.id(), &[(xcb::GC_FOREGROUND, screen.black_pixel())]);
+		xcb::create_gc(&display, gc, window.id(), &[
+			(xcb::GC_FOREGROUND, screen.black_pixel()),
+			(xcb::GC_GRAPHICS_EXPOSURES, 0)]);
 
 		display.flush();
 
@@ -79,10 +98,15 @@ impl Window {
 			window:  window,
 			gc:      gc,
 			cursor:  cursor,
+			overlay: None,
+
+			background: None,
+			offscreen:  None,
 
 			locked:   false,
 			keyboard: false,
 			pointer:  false,
+			failure:  None,
 		})
 	}
 
@@ -101,12 +125,23 @@ impl Window {
 		self.pointer
 	}
 
+	/// Why the keyboard could not be grabbed, if it wasn't.
+	pub fn failure(&self) -> Option<&str> {
+		if self.keyboard {
+			None
+		}
+		else {
+			self.failure.as_ref().map(AsRef::as_ref)
+		}
+	}
+
 	/// Sanitize the window.
 	pub fn sanitize(&mut self) {
 		if self.locked {
 			// Try to grab the keyboard again in case it wasn't grabbed when locking.
 			if !self.keyboard && self.grab(Grab::Keyboard).is_ok() {
 				self.keyboard = true;
+				self.failure  = None;
 			}
 
 			// Try to grab the pointer again in case it wasn't grabbed when locking.
@@ -179,6 +214,23 @@ impl Window {
 			return Ok(());
 		}
 
+		// With a compositing manager move into the overlay window, so nothing
+		// can be drawn above the locker.
+		//
+		// Isolated savers could otherwise find the window by walking the tree
+		// from the root of their offscreen window, so it's always hidden there.
+		let overlay = if self.offscreen.is_some() {
+			self.display.hidden(self.screen())
+		}
+		else {
+			self.display.overlay(self.screen())
+		};
+
+		if let Some(overlay) = overlay {
+			xcb::reparent_window(&self.display, self.id(), overlay, 0, 0);
+			self.overlay = Some(overlay);
+		}
+
 		// Map the window and make sure it's raised.
 		xcb::map_window(&self.display, self.id());
 		xcb::configure_window(&self.display, self.id(), &[
@@ -193,6 +245,9 @@ impl Window {
 		if !self.keyboard || !self.pointer {
 			warn!("could not grab keyboard or pointer, trying to change focus");
 
+			// The focused application is the most likely culprit.
+			let focused = self.focused();
+
 			xcb::set_input_focus(&self.display, xcb::INPUT_FOCUS_POINTER_ROOT as u8, self.id(), xcb::CURRENT_TIME);
 			self.flush();
 
@@ -200,7 +255,21 @@ impl Window {
 			// other applications may be stealing our thunder.
 			if !self.keyboard {
 				if let Err(err) = self.try_grab(Grab::Keyboard, 500) {
-					warn!("could not grab pointer: {:?}", err);
+					warn!("could not grab keyboard: {:?}", err);
+
+					let reason = match err {
+						error::Error::Grab(error::Grab::Conflict) => "already grabbed",
+						error::Error::Grab(error::Grab::Frozen)   => "frozen",
+						error::Error::Grab(error::Grab::Unmapped) => "not viewable",
+						_                                         => "request failed",
+					};
+
+					self.failure = Some(if let Some(name) = focused {
+						format!("could not grab the keyboard ({}), {} had focus", reason, name)
+					}
+					else {
+						format!("could not grab the keyboard ({})", reason)
+					});
 				}
 				else {
 					self.keyboard = true;
@@ -229,9 +298,66 @@ impl Window {
 
 		self.locked = true;
 
+		// Show what the isolated saver already drew, only changes are presented
+		// after this.
+		if let Some(offscreen) = self.offscreen {
+			xcb::copy_area(&self.display, offscreen.pixmap, self.id(), self.gc,
+				0, 0, 0, 0, offscreen.width as u16, offscreen.height as u16);
+
+			self.flush();
+		}
+
 		Ok(())
 	}
 
+	/// Find the name of the application owning the focused window.
+	fn focused(&self) -> Option<String> {
+		let mut window = xcb::get_input_focus(&self.display).get_reply().ok()?.focus();
+
+		// Walk up to the first window with a class, which is the client window.
+		while window != xcb::NONE && window != xcb::INPUT_FOCUS_POINTER_ROOT && window != self.root() {
+			let reply = xcb::get_property(&self.display, false, window,
+				xcb::ATOM_WM_CLASS, xcb::ATOM_STRING, 0, 256).get_reply().ok()?;
+
+			// The class is the second of the two NUL terminated strings.
+			if let Some(class) = reply.value::<u8>().split(|&c| c == 0).nth(1) {
+				if !class.is_empty() {
+					return Some(String::from_utf8_lossy(class).into_owned());
+				}
+			}
+
+			window = xcb::query_tree(&self.display, window).get_reply().ok()?.parent();
+		}
+
+		None
+	}
+
+	/// Show a message in the middle of the window.
+	pub fn message(&mut self, text: &str) {
+		let screen = self.display.get_setup().roots().nth(self.screen() as usize).unwrap();
+		let font   = self.display.generate_id();
+		let gc     = self.display.generate_id();
+
+		xcb::open_font(&self.display, font, "fixed");
+		xcb::create_gc(&self.display, gc, self.id(), &[
+			(xcb::GC_FOREGROUND, screen.white_pixel()),
+			(xcb::GC_BACKGROUND, screen.black_pixel()),
+			(xcb::GC_FONT, font)]);
+
+		self.blank();
+
+		// The fixed font is 6 pixels wide.
+		let (width, height) = self.dimensions();
+		let x = (width as i32 - text.len() as i32 * 6).max(0) / 2;
+		let y = height as i32 / 2;
+
+		xcb::image_text_8(&self.display, self.id(), gc, x as i16, y as i16, text);
+		xcb::free_gc(&self.display, gc);
+		xcb::close_font(&self.display, font);
+
+		self.flush();
+	}
+
 	/// Notify the window the power status changed.
 	pub fn power(&mut self, value: bool) {
 		if !value {
@@ -240,12 +366,163 @@ impl Window {
 		}
 	}
 
-	/// Make the window solid black.
+	/// Get the offscreen window for an isolated saver, creating it if needed.
+	///
+	/// The saver draws into it without ever knowing the real window, and the
+	/// contents are copied over with `present`.
+	pub fn offscreen(&mut self) -> error::Result<u32> {
+		if let Some(offscreen) = self.offscreen {
+			return Ok(offscreen.id);
+		}
+
+		if self.display.composite().is_none() {
+			return Err(error::X::MissingExtension.into());
+		}
+
+		let screen          = self.display.get_setup().roots().nth(self.screen() as usize).unwrap();
+		let (width, height) = self.dimensions();
+		let id              = self.display.generate_id();
+
+		// Keep it outside of the screen in case a compositing manager shows it.
+		xcb::create_window(&self.display, xcb::COPY_FROM_PARENT as u8, id, self.root(),
+			-(width as i32) as i16, 0, width as u16, height as u16,
+			0, xcb::WINDOW_CLASS_INPUT_OUTPUT as u16, screen.root_visual(), &[
+				(xcb::CW_BACK_PIXEL, screen.black_pixel()),
+				(xcb::CW_OVERRIDE_REDIRECT, 1)]);
+
+		xcb::change_property(&self.display, xcb::PROP_MODE_REPLACE as u8, id,
+			xcb::intern_atom(&self.display, false, "SCREENRUSTER_SAVER").get_reply()?.atom(),
+			xcb::ATOM_CARDINAL, 32, &[self.screen()]);
+
+		// A compositing manager may already be redirecting it, either way it ends
+		// up with a pixmap holding its contents.
+		if let Err(err) = xcb::composite::redirect_window_checked(&self.display, id,
+			xcb::composite::REDIRECT_MANUAL as u8).request_check()
+		{
+			warn!("could not redirect offscreen window: {:?}", err);
+		}
+
+		xcb::map_window(&self.display, id);
+
+		let pixmap = self.display.generate_id();
+		xcb::composite::name_window_pixmap(&self.display, id, pixmap);
+
+		// Without the Damage extension the whole window is copied every frame.
+		let damage = if self.display.damage().is_some() {
+			let damage = self.display.generate_id();
+			let region = self.display.generate_id();
+
+			xcb::damage::create(&self.display, damage, id, xcb::damage::REPORT_LEVEL_NON_EMPTY as u8);
+			xcb::xfixes::create_region(&self.display, region, &[]);
+
+			Some((damage, region))
+		}
+		else {
+			None
+		};
+
+		self.offscreen = Some(Offscreen { id, pixmap, width, height, damage });
+		self.flush();
+
+		Ok(id)
+	}
+
+	/// Check if the given drawable is the offscreen window.
+	pub fn owns_offscreen(&self, drawable: u32) -> bool {
+		self.offscreen.map_or(false, |o| o.id == drawable)
+	}
+
+	/// Copy the contents of the offscreen window to the window, only the parts
+	/// that changed since the last time if they're tracked.
+	pub fn present(&self) {
+		if let Some(offscreen) = self.offscreen {
+			if let Some((damage, region)) = offscreen.damage {
+				xcb::damage::subtract(&self.display, damage, xcb::NONE, region);
+				xcb::xfixes::set_gc_clip_region(&self.display, self.gc, region, 0, 0);
+			}
+
+			xcb::copy_area(&self.display, offscreen.pixmap, self.id(), self.gc,
+				0, 0, 0, 0, offscreen.width as u16, offscreen.height as u16);
+
+			if offscreen.damage.is_some() {
+				xcb::xfixes::set_gc_clip_region(&self.display, self.gc, xcb::NONE, 0, 0);
+			}
+
+			self.flush();
+		}
+	}
+
+	/// Resize the window, and the offscreen window if any.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		self.window.resize(width, height);
+
+		if let Some(offscreen) = self.offscreen.take() {
+			xcb::configure_window(&self.display, offscreen.id, &[
+				(xcb::CONFIG_WINDOW_X as u16, -(width as i32) as u32),
+				(xcb::CONFIG_WINDOW_WIDTH as u16, width),
+				(xcb::CONFIG_WINDOW_HEIGHT as u16, height)]);
+
+			// A new pixmap is allocated when the size changes.
+			xcb::free_pixmap(&self.display, offscreen.pixmap);
+
+			let pixmap = self.display.generate_id();
+			xcb::composite::name_window_pixmap(&self.display, offscreen.id, pixmap);
+
+			self.offscreen = Some(Offscreen { pixmap, width, height, .. offscreen });
+			self.flush();
+		}
+	}
+
+	/// Use the snapshot as the window contents instead of black, or go back to
+	/// black.
+	pub fn background(&mut self, snapshot: Option<&Snapshot>) {
+		if let Some(pixmap) = self.background.take() {
+			xcb::change_window_attributes(&self.display, self.id(), &[
+				(xcb::CW_BACK_PIXEL, self.black())]);
+
+			xcb::free_pixmap(&self.display, pixmap);
+		}
+
+		if let Some(snapshot) = snapshot {
+			let screen = self.display.get_setup().roots().nth(self.screen() as usize).unwrap();
+			let pixmap = self.display.generate_id();
+
+			xcb::create_pixmap(&self.display, screen.root_depth(), pixmap, self.id(),
+				snapshot.width() as u16, snapshot.height() as u16);
+
+			// Upload the image in strips to stay below the maximum request length,
+			// which is in 4 bytes units and includes the request header.
+			let maximum = self.display.get_maximum_request_length() as usize * 4 - 24;
+			let stride  = snapshot.width() as usize * 4;
+			let rows    = (maximum / stride).max(1);
+
+			for (index, strip) in snapshot.data().chunks(rows * stride).enumerate() {
+				xcb::put_image(&self.display, xcb::IMAGE_FORMAT_Z_PIXMAP as u8, pixmap, self.gc,
+					snapshot.width() as u16, (strip.len() / stride) as u16,
+					0, (index * rows) as i16, 0, screen.root_depth(), strip);
+			}
+
+			xcb::change_window_attributes(&self.display, self.id(), &[
+				(xcb::CW_BACK_PIXMAP, pixmap)]);
+
+			self.background = Some(pixmap);
+		}
+
+		self.flush();
+	}
+
+	/// Make the window solid black, or show the background if any.
 	pub fn blank(&mut self) {
 		let (width, height) = self.dimensions();
 
-		xcb::poly_fill_rectangle(&self.display, self.id(), self.gc, &[
-			xcb::Rectangle::new(0, 0, width as u16, height as u16)]);
+		if let Some(pixmap) = self.background {
+			xcb::copy_area(&self.display, pixmap, self.id(), self.gc,
+				0, 0, 0, 0, width as u16, height as u16);
+		}
+		else {
+			xcb::poly_fill_rectangle(&self.display, self.id(), self.gc, &[
+				xcb::Rectangle::new(0, 0, width as u16, height as u16)]);
+		}
 
 		self.flush();
 	}
@@ -265,6 +542,23 @@ impl Window {
 		xcb::unmap_window(&self.display, self.id());
 		self.locked = false;
 
+		// The saver is done with the offscreen window.
+		if let Some(offscreen) = self.offscreen.take() {
+			if let Some((damage, region)) = offscreen.damage {
+				xcb::damage::destroy(&self.display, damage);
+				xcb::xfixes::destroy_region(&self.display, region);
+			}
+
+			xcb::free_pixmap(&self.display, offscreen.pixmap);
+			xcb::destroy_window(&self.display, offscreen.id);
+		}
+
+		// Give the overlay window back to the compositing manager.
+		if self.overlay.take().is_some() {
+			xcb::reparent_window(&self.display, self.id(), self.root(), 0, 0);
+			xcb::composite::release_overlay_window(&self.display, self.root());
+		}
+
 		self.flush();
 
 		Ok(())
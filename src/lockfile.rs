@@ -0,0 +1,159 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH, Duration};
+
+use toml;
+
+/// Why the screen was locked.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Reason {
+	/// The lock timer expired.
+	Timer,
+
+	/// Locking was requested through the interface.
+	Request,
+
+	/// The system was coming back from sleep.
+	Suspend,
+
+	/// The daemon was restarted while locked.
+	Restart,
+}
+
+impl fmt::Display for Reason {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match *self {
+			Reason::Timer   => "timer",
+			Reason::Request => "request",
+			Reason::Suspend => "suspend",
+			Reason::Restart => "restart",
+		})
+	}
+}
+
+/// The locked state as left by a previous daemon.
+#[derive(Clone, Debug)]
+pub struct Lockfile {
+	pub since:  SystemTime,
+	pub reason: String,
+}
+
+/// The path of the lock file, `None` if `$XDG_RUNTIME_DIR` is not set.
+pub fn path() -> Option<PathBuf> {
+	env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("screenruster").join("locked"))
+}
+
+/// Read the lock file if present.
+pub fn read() -> Option<Lockfile> {
+	let mut content = String::new();
+	File::open(path()?).ok()?.read_to_string(&mut content).ok()?;
+
+	// Even if unreadable the file being there means the screen was locked.
+	let table = content.parse::<toml::Value>().unwrap_or(toml::Value::Table(Default::default()));
+
+	Some(Lockfile {
+		since: UNIX_EPOCH + Duration::from_secs(table.get("since")
+			.and_then(|v| v.as_integer()).unwrap_or(0) as u64),
+
+		reason: table.get("reason")
+			.and_then(|v| v.as_str()).unwrap_or("unknown").into(),
+	})
+}
+
+/// Write the lock file, if there's already one it keeps when and why the
+/// screen was locked, like when locking again after a restart.
+pub fn write(reason: Reason) -> io::Result<()> {
+	let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+
+	let (since, reason) = match read() {
+		Some(previous) if previous.since > UNIX_EPOCH =>
+			(previous.since, previous.reason),
+
+		_ =>
+			(SystemTime::now(), reason.to_string()),
+	};
+
+	let since = since.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0));
+
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	let mut file = File::create(path)?;
+	writeln!(file, "since  = {}", since.as_secs())?;
+	writeln!(file, "reason = {}", toml::Value::String(reason))?;
+	file.sync_all()
+}
+
+/// Remove the lock file.
+pub fn remove() -> io::Result<()> {
+	let path = if let Some(path) = path() {
+		path
+	}
+	else {
+		return Ok(());
+	};
+
+	match fs::remove_file(path) {
+		Err(ref err) if err.kind() == io::ErrorKind::NotFound =>
+			Ok(()),
+
+		result =>
+			result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs;
+	use std::process;
+
+	use super::*;
+
+	#[test]
+	fn relock() {
+		let dir = env::temp_dir().join(format!("screenruster-{}", process::id()));
+		env::set_var("XDG_RUNTIME_DIR", &dir);
+
+		assert!(read().is_none());
+
+		write(Reason::Timer).unwrap();
+		let previous = read().unwrap();
+		assert_eq!(previous.reason, "timer");
+
+		// Locking again keeps the original lock.
+		write(Reason::Restart).unwrap();
+		let current = read().unwrap();
+		assert_eq!(current.reason, "timer");
+		assert_eq!(current.since, previous.since);
+
+		remove().unwrap();
+		assert!(read().is_none());
+
+		write(Reason::Restart).unwrap();
+		assert_eq!(read().unwrap().reason, "restart");
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
@@ -15,9 +15,18 @@
 // You should have received a copy of the GNU General Public License
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::env;
+use std::io;
+use std::panic;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use std::os::unix::process::CommandExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use clap::{ArgMatches, Arg, App, SubCommand};
-use channel::select;
-use log::info;
+use channel::{self, Receiver, select};
+use log::{debug, info, warn, error};
 
 mod error;
 
@@ -42,6 +51,11 @@ use interface::Interface;
 mod timer;
 use timer::Timer;
 
+mod state;
+use state::State;
+
+mod lockfile;
+
 fn main() {
 	env_logger::init();
 
@@ -85,6 +99,8 @@ fn main() {
 				.required(true)
 				.index(1)
 				.help("The previously returned cookie.")))
+		.subcommand(SubCommand::with_name("status")
+			.about("Show the status of the daemon."))
 		.subcommand(SubCommand::with_name("preview")
 			.about("Preview a saver.")
 			.arg(Arg::with_name("config")
@@ -102,7 +118,11 @@ fn main() {
 				.short("c")
 				.long("config")
 				.help("The path to the configuration file.")
-				.takes_value(true)));
+				.takes_value(true))
+			.arg(Arg::with_name("locked")
+				.long("locked")
+				.hidden(true)
+				.help("Start with the screen locked.")));
 
 	let matches = app.clone().get_matches();
 
@@ -137,6 +157,11 @@ fn main() {
 		("resume", Some(submatches)) =>
 			Interface::resume(submatches.value_of("COOKIE").unwrap().parse::<u32>().unwrap()),
 
+		("status", Some(_)) =>
+			Interface::status().map(|v| for (key, value) in v {
+				println!("{}: {}", key, value);
+			}),
+
 		("preview", Some(submatches)) =>
 			preview(submatches),
 
@@ -175,6 +200,9 @@ fn exit<T>(value: error::Result<T>) -> T {
 			Error::DBus(_) =>
 				error!(11, "The daemon is not running."),
 
+			Error::X(error::X::Disconnected) =>
+				error!(12, "The connection to the X server was lost."),
+
 			ref err =>
 				error!(255, err),
 		}
@@ -196,387 +224,282 @@ fn preview(matches: &ArgMatches) -> error::Result<()> {
 	Ok(())
 }
 
-fn daemon(matches: &ArgMatches) -> error::Result<()> {
-	use std::time::{Instant, SystemTime};
-	use std::collections::HashSet;
-	use rand::Rng;
-
-	// Timer report IDs.
-	const GET_ACTIVE_TIME:       u64 = 1;
-	const GET_SESSION_IDLE:      u64 = 2;
-	const GET_SESSION_IDLE_TIME: u64 = 3;
+/// Whether the screen is locked, checked when the daemon panics.
+static LOCKED: AtomicBool = AtomicBool::new(false);
 
-	// How many seconds to wait before acting on an Activity after one was
-	// already acted upon.
-	const ACTIVATION: u64 = 1;
+/// Replace the current process with a daemon that locks right away.
+fn reexec(config: Option<&str>) -> io::Error {
+	let path = match env::current_exe() {
+		Ok(path) => path,
+		Err(err) => return err,
+	};
 
-	fn insert(set: &mut HashSet<u32>) -> u32 {
-		loop {
-			let cookie = rand::thread_rng().gen();
+	let mut command = process::Command::new(path);
+	command.arg("daemon").arg("--locked");
 
-			if set.contains(&cookie) {
-				continue;
-			}
-
-			set.insert(cookie);
-
-			return cookie;
-		}
+	if let Some(config) = config {
+		command.arg("--config").arg(config);
 	}
 
-	let config    = Config::load(matches.value_of("config"))?;
-	let timer     = Timer::spawn(config.timer())?;
-	let auth      = Auth::spawn(config.auth())?;
-	let interface = Interface::spawn(config.interface())?;
-	let locker    = Locker::spawn(config.clone())?;
-
-	let mut locked    = None::<Instant>;
-	let mut started   = None::<Instant>;
-	let mut blanked   = None::<Instant>;
-	let mut suspended = None::<SystemTime>;
-
-	let mut inhibitors = HashSet::new();
-	let mut throttlers = HashSet::new();
-	let mut suspenders = HashSet::new();
-
-	macro_rules! act {
-		(suspend) => (
-			act!(suspend SystemTime::now())
-		);
+	command.exec()
+}
 
-		(suspend $time:expr) => (
-			if suspenders.is_empty() && suspended.is_none() {
-				timer.suspend($time).unwrap();
-			}
-		);
+/// Start the locker in the background, retrying until the X server is there.
+fn start(config: Config) -> Receiver<Locker> {
+	let (sender, receiver) = channel::bounded(1);
 
-		(resume) => (
-			if suspenders.is_empty() && suspended.is_some() {
-				if blanked.is_some() {
-					act!(unblank);
+	thread::spawn(move || {
+		loop {
+			match Locker::spawn(config.clone()) {
+				Ok(value) => {
+					let _ = sender.send(value);
+					break;
 				}
 
-				timer.resume().unwrap();
+				Err(err) => {
+					debug!("could not start locker: {:?}", err);
+					thread::sleep(Duration::from_secs(1));
+				}
 			}
-		);
-
-		(blank) => (
-			blanked = Some(Instant::now());
-
-			locker.power(false).unwrap();
-			timer.blanked().unwrap();
-		);
-
-		(unblank) => (
-			blanked = None;
-
-			locker.power(true).unwrap();
-			timer.unblanked().unwrap();
-		);
-
-		(start) => (
-			started = Some(Instant::now());
+		}
+	});
 
-			locker.start().unwrap();
-			interface.signal(interface::Signal::Active(true)).unwrap();
-			timer.started().unwrap();
-		);
+	receiver
+}
 
-		(lock) => (
-			locked = Some(Instant::now());
+/// Run a hook in the background with the reason in `$SCREENRUSTER_REASON`.
+fn hook(command: &str, reason: &str) -> io::Result<()> {
+	let mut child = process::Command::new("sh")
+		.arg("-c").arg(command)
+		.env("SCREENRUSTER_REASON", reason)
+		.spawn()?;
 
-			locker.lock().unwrap();
-			timer.locked().unwrap();
-		);
+	thread::spawn(move || child.wait());
 
-		(stop) => (
-			locker.stop().unwrap();
-		);
+	Ok(())
+}
 
-		(stopped) => (
-			started = None;
-			locked  = None;
+fn daemon(matches: &ArgMatches) -> error::Result<()> {
+	let path   = matches.value_of("config").map(String::from);
+	let config = Config::load(path.as_ref())?;
+
+	// Lock right away if the previous daemon was locked, components are then
+	// retried until they start.
+	let mut state = State::new(config.clone());
+	let initial   = if let Some(previous) = lockfile::read() {
+		info!("locked since {:?} by {}, locking again", previous.since, previous.reason);
+		state.relock()
+	}
+	else if matches.is_present("locked") {
+		state.relock()
+	}
+	else {
+		Vec::new()
+	};
 
-			interface.signal(interface::Signal::Active(false)).unwrap();
-			timer.stopped().unwrap();
-		);
+	LOCKED.store(state.is_locked(), Ordering::SeqCst);
 
-		(auth < $value:expr) => (
-			interface.signal(interface::Signal::AuthenticationRequest(true)).unwrap();
-			auth.authenticate($value).unwrap();
-		);
+	// Never leave the screen unlocked because the daemon itself panicked.
+	{
+		let path     = path.clone();
+		let previous = panic::take_hook();
 
-		(auth success) => (
-			locker.auth(true).unwrap();
-			interface.signal(interface::Signal::AuthenticationRequest(false)).unwrap();
-		);
+		panic::set_hook(Box::new(move |info| {
+			previous(info);
 
-		(auth failure) => (
-			locker.auth(false).unwrap();
-			interface.signal(interface::Signal::AuthenticationRequest(false)).unwrap();
-		);
+			if thread::current().name() == Some("main") && LOCKED.load(Ordering::SeqCst) {
+				error!("daemon panicked while locked, restarting: {:?}", reexec(path.as_ref().map(String::as_ref)));
+			}
+		}));
 	}
 
-	loop {
-		select! {
-			// Locker events.
-			recv(locker) -> event => {
-				match event.unwrap() {
-					// Register timeout.
-					locker::Response::Timeout(what) => {
-						timer.timeout(what).unwrap();
+	// Spawn a component, retrying while the screen is locked.
+	macro_rules! spawn {
+		($name:expr, $body:expr) => (
+			loop {
+				match $body {
+					Ok(value) => {
+						break value;
 					}
 
-					// On system activity.
-					locker::Response::Activity => {
-						if suspended.is_some() {
-							continue;
-						}
-
-						// Always reset the blank timer.
-						timer.reset(timer::Event::Blank).unwrap();
-
-						if blanked.is_some() {
-							act!(unblank);
+					Err(err) => {
+						if !state.is_locked() {
+							return Err(err);
 						}
 
-						// If the saver has started but the screen is not locked, unlock
-						// it, otherwise just reset the timers.
-						if let Some(at) = started {
-							if locked.is_none() && at.elapsed().as_secs() >= ACTIVATION {
-								act!(stop);
-							}
-						}
-						else {
-							timer.reset(timer::Event::Idle).unwrap();
-						}
-					}
-
-					// Try authorization.
-					locker::Response::Password(pwd) => {
-						act!(auth < pwd);
-					}
-
-					locker::Response::Stopped => {
-						act!(stopped);
+						error!("could not start {}: {:?}", $name, err);
+						thread::sleep(Duration::from_secs(1));
 					}
 				}
-			},
+			}
+		);
+	}
 
-			// Authentication events.
-			recv(auth) -> event => {
-				match event.unwrap() {
-					auth::Response::Success => {
-						info!("authorization: success");
+	let mut timer     = spawn!("timer", Timer::spawn(config.timer(), config.power()));
+	let mut auth      = spawn!("auth", Auth::spawn(config.auth()));
+	let mut interface = spawn!("interface", Interface::spawn(config.interface()));
 
-						act!(auth success);
-						act!(stop);
-					}
+	// The locker is started in the background while the X server is missing, so
+	// the other components keep working in the meantime.
+	let mut starting = None::<Receiver<Locker>>;
+	let mut locker   = match Locker::spawn(config.clone()) {
+		Ok(value) => {
+			Some(value)
+		}
 
-					auth::Response::Failure => {
-						info!("authorization: failure");
+		Err(err) => {
+			if !state.is_locked() {
+				return Err(err);
+			}
 
-						act!(auth failure);
-					}
-				}
-			},
+			error!("could not start locker: {:?}", err);
+			starting = Some(start(config.clone()));
 
-			// DBus events.
-			recv(interface) -> event => {
-				match event.unwrap() {
-					interface::Request::Reload(source) => {
-						config.reset();
-						interface.response(interface::Response::Reload(
-							config.reload(source).is_ok())).unwrap();
-					}
+			None
+		}
+	};
 
-					interface::Request::Lock => {
-						if started.is_none() {
-							act!(start);
-						}
+	let never = (channel::never::<locker::Response>(), channel::never::<Locker>());
 
-						if locked.is_none() {
-							act!(lock);
-						}
-					}
+	// Send the commands, errors are ignored since a dead component is noticed
+	// and restarted when receiving from it.
+	macro_rules! execute {
+		($commands:expr) => (
+			for command in $commands {
+				match command {
+					state::Command::Locker(request) =>
+						locker.as_ref().and_then(|l| l.send(request).ok()),
 
-					// TODO: Implement cycling.
-					interface::Request::Cycle => (),
+					state::Command::Timer(request) =>
+						timer.send(request).ok(),
 
-					interface::Request::SimulateUserActivity => {
-						locker.activity().unwrap();
-					}
+					state::Command::Authenticate(password) =>
+						auth.authenticate(password).ok(),
 
-					interface::Request::Inhibit { .. } => {
-						interface.response(interface::Response::Inhibit(insert(&mut inhibitors))).unwrap();
-					}
+					state::Command::Signal(signal) =>
+						interface.signal(signal).ok(),
 
-					interface::Request::UnInhibit(cookie) => {
-						if inhibitors.contains(&cookie) {
-							inhibitors.remove(&cookie);
-						}
-					}
+					state::Command::Response(response) =>
+						interface.response(response).ok(),
 
-					interface::Request::Throttle { .. } => {
-						if throttlers.is_empty() && !config.saver().throttle() {
-							locker.throttle(true).unwrap();
-						}
+					state::Command::Persist(Some(reason)) =>
+						lockfile::write(reason).map_err(|err| warn!("could not write lock file: {}", err)).ok(),
 
-						interface.response(interface::Response::Throttle(insert(&mut throttlers))).unwrap();
-					}
+					state::Command::Persist(None) =>
+						lockfile::remove().map_err(|err| warn!("could not remove lock file: {}", err)).ok(),
 
-					interface::Request::UnThrottle(cookie) => {
-						if throttlers.contains(&cookie) {
-							throttlers.remove(&cookie);
+					state::Command::Hook(command, reason) =>
+						hook(&command, &reason).map_err(|err| warn!("could not run hook: {}", err)).ok(),
+				};
+			}
+		);
+	}
 
-							if throttlers.is_empty() && !config.saver().throttle() {
-								locker.throttle(false).unwrap();
-							}
-						}
-					}
+	// Restart a component that stopped unexpectedly.
+	macro_rules! restart {
+		($var:ident => $component:ident, $body:expr) => ({
+			error!("{} stopped unexpectedly, restarting", stringify!($var));
 
-					interface::Request::SetActive(active) => {
-						if active {
-							if started.is_none() {
-								act!(start);
-							}
-						}
-						else {
-							if started.is_some() && locked.is_none() {
-								act!(stop);
-							}
-						}
-					}
+			// Release whatever the old one is holding first.
+			drop($var);
+			$var = spawn!(stringify!($var), $body);
+			execute!(state.restart(state::Component::$component));
+		});
+	}
 
-					interface::Request::GetActive => {
-						interface.response(interface::Response::Active(started.is_some())).unwrap();
-					}
+	execute!(initial);
 
-					interface::Request::GetActiveTime => {
-						timer.report(GET_ACTIVE_TIME).unwrap();
+	loop {
+		let event = select! {
+			recv(locker.as_ref().map_or(&never.0, |l| &**l)) -> event => match event {
+				// Either give up or wait for the X server to come back, the screen
+				// is never left unlocked because of it.
+				Ok(locker::Response::Disconnected) => {
+					if !config.locker().reconnect() && !state.is_locked() {
+						return Err(error::X::Disconnected.into());
 					}
 
-					interface::Request::GetSessionIdle => {
-						timer.report(GET_SESSION_IDLE).unwrap();
-					}
+					warn!("X connection lost, reconnecting");
 
-					interface::Request::GetSessionIdleTime => {
-						timer.report(GET_SESSION_IDLE_TIME).unwrap();
-					}
+					locker   = None;
+					starting = Some(start(config.clone()));
 
-					interface::Request::Suspend { .. } => {
-						act!(suspend);
+					continue;
+				}
 
-						interface.response(interface::Response::Suspend(insert(&mut suspenders))).unwrap();
-					}
+				Ok(event) => {
+					state::Event::Locker(event)
+				}
 
-					interface::Request::Resume(cookie) => {
-						if suspenders.contains(&cookie) {
-							suspenders.remove(&cookie);
+				Err(_) => {
+					error!("locker stopped unexpectedly, restarting");
 
-							act!(resume);
-						}
-					}
+					locker   = None;
+					starting = Some(start(config.clone()));
 
-					interface::Request::PrepareForSleep(time) => {
-						if let Some(time) = time {
-							match config.locker().on_suspend() {
-								config::OnSuspend::Ignore |
-								config::OnSuspend::Activate |
-								config::OnSuspend::Lock => (),
-
-								config::OnSuspend::UseSystemTime => {
-									act!(suspend time);
-								}
-							}
-						}
-						else {
-							match config.locker().on_suspend() {
-								config::OnSuspend::Ignore => (),
-
-								config::OnSuspend::UseSystemTime => {
-									act!(resume);
-								}
-
-								config::OnSuspend::Activate => {
-									act!(start);
-								}
-
-								config::OnSuspend::Lock => {
-									act!(start);
-									act!(lock);
-								}
-							}
-						}
-					}
+					continue;
 				}
 			},
 
-			// Timer events.
-			recv(timer) -> event => {
-				match event.unwrap() {
-					timer::Response::Report { id: GET_ACTIVE_TIME, started, .. } => {
-						interface.response(interface::Response::ActiveTime(started.map_or(0, |i| i.elapsed().as_secs()))).unwrap();
-					}
+			recv(starting.as_ref().unwrap_or(&never.1)) -> value => {
+				starting = None;
 
-					timer::Response::Report { id: GET_SESSION_IDLE, idle, .. } => {
-						interface.response(interface::Response::SessionIdle(idle.elapsed().as_secs() >= 5)).unwrap();
-					}
+				if let Ok(value) = value {
+					info!("locker started");
 
-					timer::Response::Report { id: GET_SESSION_IDLE_TIME, idle, .. } => {
-						interface.response(interface::Response::SessionIdleTime(idle.elapsed().as_secs())).unwrap();
-					}
+					locker = Some(value);
+					execute!(state.restart(state::Component::Locker));
+				}
 
-					timer::Response::Report { .. } => {
-						unreachable!();
-					}
+				continue;
+			},
 
-					timer::Response::Timeout { id } => {
-						locker.timeout(id).unwrap();
-					}
+			recv(auth) -> event => match event {
+				Ok(event) => {
+					state::Event::Auth(event)
+				}
 
-					timer::Response::Suspended(time) => {
-						suspended = Some(time);
-					}
+				Err(_) => {
+					restart!(auth => Auth, Auth::spawn(config.auth()));
+					continue;
+				}
+			},
 
-					timer::Response::Resumed => {
-						suspended = None;
-					}
+			recv(interface) -> event => match event {
+				Ok(event) => {
+					state::Event::Interface(event)
+				}
 
-					timer::Response::Heartbeat(idle) => {
-						locker.sanitize().unwrap();
+				Err(_) => {
+					restart!(interface => Interface, Interface::spawn(config.interface()));
+					continue;
+				}
+			},
 
-						if idle.elapsed().as_secs() > 5 {
-							interface.signal(interface::Signal::SessionIdle(true)).unwrap()
-						}
-						else {
-							interface.signal(interface::Signal::SessionIdle(false)).unwrap()
-						}
-					}
+			recv(interface.failures()) -> failure => {
+				if let Ok(name) = failure {
+					error!("interface {} handler panicked", name);
+				}
 
-					timer::Response::Start => {
-						if inhibitors.is_empty() {
-							act!(start);
-						}
-						else {
-							timer.stopped().unwrap();
-						}
-					}
+				restart!(interface => Interface, Interface::spawn(config.interface()));
+				continue;
+			},
 
-					timer::Response::Lock => {
-						act!(lock);
-					}
+			recv(timer) -> event => match event {
+				Ok(event) => {
+					state::Event::Timer(event)
+				}
 
-					timer::Response::Blank => {
-						if inhibitors.is_empty() {
-							act!(blank);
-						}
-						else {
-							timer.unblanked().unwrap();
-						}
-					}
+				Err(_) => {
+					restart!(timer => Timer, Timer::spawn(config.timer(), config.power()));
+					continue;
 				}
-			}
-		}
+			},
+		};
+
+		execute!(state.handle(event));
+		LOCKED.store(state.is_locked(), Ordering::SeqCst);
+
+		debug!("{:?}", state);
 	}
 }
@@ -17,9 +17,16 @@
 
 use std::sync::Arc;
 use std::env;
+use std::ptr;
+use std::slice;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
 
 use xcb;
 use xkb;
+use log::warn;
 
 use crate::error;
 use super::Display;
@@ -37,6 +44,26 @@ pub struct Keyboard {
 	#[allow(dead_code)]
 	table:   xkb::compose::Table,
 	compose: xkb::compose::State,
+
+	// The actions removed from the server keymap, by key code and position,
+	// they're also saved to a file so they can be restored by another process.
+	blocked: Option<Vec<(u8, usize, [u8; 8])>>,
+}
+
+/// What typing a key produced.
+#[derive(Clone, Debug)]
+pub enum Text {
+	/// Nothing, like for modifiers.
+	None,
+
+	/// A compose sequence is pending.
+	Composing,
+
+	/// The compose sequence was cancelled.
+	Cancelled,
+
+	/// The string from the key or the finished compose sequence.
+	String(String),
 }
 
 unsafe impl Send for Keyboard { }
@@ -101,7 +128,7 @@ impl Keyboard {
 			(table, state)
 		};
 
-		Ok(Keyboard { display, context, device, keymap, state, table, compose })
+		Ok(Keyboard { display, context, device, keymap, state, table, compose, blocked: None })
 	}
 
 	/// Get the extension data.
@@ -121,6 +148,13 @@ impl Keyboard {
 			xcb::xkb::NEW_KEYBOARD_NOTIFY | xcb::xkb::MAP_NOTIFY => {
 				self.keymap = xkb::x11::keymap(&self.display, self.device, &self.context, Default::default()).unwrap();
 				self.state  = xkb::x11::state(&self.display, self.device, &self.keymap).unwrap();
+
+				// A new keymap may bring the actions back.
+				if self.blocked.is_some() {
+					if let Err(err) = self.strip() {
+						warn!("could not block VT switching: {:?}", err);
+					}
+				}
 			}
 
 			xcb::xkb::STATE_NOTIFY => {
@@ -148,4 +182,255 @@ impl Keyboard {
 	pub fn string(&self, code: u8) -> Option<String> {
 		self.state.key(code).utf8()
 	}
+
+	/// Translate a key code to text, going through compose sequences and dead
+	/// keys.
+	pub fn text(&mut self, code: u8) -> Text {
+		if let Some(symbol) = self.symbol(code) {
+			self.compose.feed(symbol);
+
+			match self.compose.status() {
+				xkb::compose::Status::Composing => {
+					return Text::Composing;
+				}
+
+				xkb::compose::Status::Cancelled => {
+					self.compose.reset();
+					return Text::Cancelled;
+				}
+
+				xkb::compose::Status::Composed => {
+					let string = self.compose.utf8();
+					self.compose.reset();
+
+					return string.map_or(Text::None, Text::String);
+				}
+
+				xkb::compose::Status::Nothing => ()
+			}
+		}
+
+		self.string(code).map_or(Text::None, Text::String)
+	}
+
+	/// Drop any pending compose sequence.
+	pub fn reset(&mut self) {
+		self.compose.reset();
+	}
+
+	/// Remove from the server keymap the actions to switch VT and to terminate
+	/// the server, or restore them.
+	pub fn block(&mut self, value: bool) -> error::Result<()> {
+		if value {
+			if self.blocked.is_none() {
+				self.blocked = Some(Vec::new());
+			}
+
+			self.strip()
+		}
+		else if let Some(removed) = self.blocked.take() {
+			if removed.is_empty() {
+				return Ok(());
+			}
+
+			// Keep the saved actions around until they're actually back.
+			if let Err(err) = self.restore(&removed) {
+				self.blocked = Some(removed);
+				return Err(err);
+			}
+
+			if let Some(path) = path() {
+				let _ = fs::remove_file(path);
+			}
+
+			Ok(())
+		}
+		else {
+			Ok(())
+		}
+	}
+
+	/// Restore the actions left removed by a locker that didn't get to do it,
+	/// because it panicked, lost the connection or the process was replaced.
+	pub fn recover(&mut self) -> error::Result<()> {
+		let mut content = String::new();
+
+		match path().map(File::open) {
+			Some(Ok(mut file)) => {
+				file.read_to_string(&mut content)?;
+			}
+
+			_ => return Ok(())
+		}
+
+		let mut removed = self.blocked.take().unwrap_or_default();
+
+		for line in content.lines() {
+			let values = line.split_whitespace().filter_map(|v| v.parse::<usize>().ok()).collect::<Vec<_>>();
+
+			if values.len() != 10 {
+				continue;
+			}
+
+			let mut action = [0u8; 8];
+			for (i, value) in values[2..].iter().enumerate() {
+				action[i] = *value as u8;
+			}
+
+			removed.push((values[0] as u8, values[1], action));
+		}
+
+		self.blocked = Some(removed);
+		self.block(false)
+	}
+
+	/// Put back the removed actions in the server keymap.
+	fn restore(&self, removed: &[(u8, usize, [u8; 8])]) -> error::Result<()> {
+		let (first, mut counts, mut actions) = self.actions()?;
+		let mut offset = 0;
+
+		// Only restore the actions nobody else changed in the meantime.
+		for (key, &count) in counts.iter().enumerate() {
+			for &(code, index, action) in removed {
+				if code as usize == first as usize + key && index < count as usize &&
+				   actions[offset + index][0] as xcb::xkb::SaType == xcb::xkb::SA_TYPE_NO_ACTION
+				{
+					actions[offset + index] = action;
+				}
+			}
+
+			offset += count as usize;
+		}
+
+		self.set_actions(first, &mut counts, &mut actions)
+	}
+
+	/// Replace the blocked actions with no actions.
+	fn strip(&mut self) -> error::Result<()> {
+		let (first, mut counts, mut actions) = self.actions()?;
+		let mut removed = Vec::new();
+		let mut offset  = 0;
+
+		for (key, &count) in counts.iter().enumerate() {
+			for index in 0 .. count as usize {
+				match actions[offset + index][0] as xcb::xkb::SaType {
+					xcb::xkb::SA_TYPE_SWITCH_SCREEN | xcb::xkb::SA_TYPE_TERMINATE => {
+						removed.push((first + key as u8, index, actions[offset + index]));
+						actions[offset + index] = [xcb::xkb::SA_TYPE_NO_ACTION as u8, 0, 0, 0, 0, 0, 0, 0];
+					}
+
+					_ => ()
+				}
+			}
+
+			offset += count as usize;
+		}
+
+		if removed.is_empty() {
+			return Ok(());
+		}
+
+		self.set_actions(first, &mut counts, &mut actions)?;
+		self.blocked.get_or_insert_with(Vec::new).extend(removed);
+		self.save();
+
+		Ok(())
+	}
+
+	/// Save the removed actions, one per line as the key code, the position and
+	/// the action bytes.
+	fn save(&self) {
+		let path = if let Some(path) = path() { path } else { return };
+		let mut content = String::new();
+
+		for &(code, index, action) in self.blocked.iter().flatten() {
+			content.push_str(&format!("{} {}", code, index));
+
+			for byte in &action {
+				content.push_str(&format!(" {}", byte));
+			}
+
+			content.push('\n');
+		}
+
+		let result = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_|
+			OpenOptions::new().write(true).create(true).truncate(true).mode(0o600)
+				.open(&path)?.write_all(content.as_bytes()));
+
+		if let Err(err) = result {
+			warn!("could not save the blocked keymap actions: {}", err);
+		}
+	}
+
+	/// Fetch the key actions from the server keymap, returning the first key
+	/// code, the number of actions per key and the actions.
+	fn actions(&self) -> error::Result<(u8, Vec<u8>, Vec<[u8; 8]>)> {
+		let setup = self.display.get_setup();
+		let reply = xcb::xkb::get_map(&self.display, self.device as xcb::xkb::DeviceSpec,
+			0, xcb::xkb::MAP_PART_KEY_ACTIONS as u16,
+			0, 0, 0, 0,
+			setup.min_keycode(), setup.max_keycode() - setup.min_keycode() + 1,
+			0, 0, 0, 0, 0, 0, 0, 0, 0).get_reply()?;
+
+		// The reply only has the actions part, the counts padded to 4 bytes
+		// followed by the actions.
+		unsafe {
+			let map     = xcb::ffi::xkb::xcb_xkb_get_map_map(reply.ptr) as *const u8;
+			let keys    = reply.n_key_actions() as usize;
+			let counts  = slice::from_raw_parts(map, keys).to_vec();
+			let actions = slice::from_raw_parts(map.add((keys + 3) & !3) as *const [u8; 8],
+				reply.total_actions() as usize).to_vec();
+
+			Ok((reply.first_key_action(), counts, actions))
+		}
+	}
+
+	/// Replace the key actions in the server keymap.
+	fn set_actions(&self, first: u8, counts: &mut [u8], actions: &mut [[u8; 8]]) -> error::Result<()> {
+		let setup  = self.display.get_setup();
+		let values = xcb::ffi::xkb::xcb_xkb_set_map_values_t {
+			types:        ptr::null_mut(),
+			syms:         ptr::null_mut(),
+			actionsCount: counts.as_mut_ptr(),
+			actions:      actions.as_mut_ptr() as *mut xcb::ffi::xkb::xcb_xkb_action_t,
+			behaviors:    ptr::null_mut(),
+			vmods:        ptr::null_mut(),
+			explicit:     ptr::null_mut(),
+			modmap:       ptr::null_mut(),
+			vmodmap:      ptr::null_mut(),
+		};
+
+		let cookie = unsafe {
+			xcb::ffi::xkb::xcb_xkb_set_map_checked(self.display.get_raw_conn(),
+				self.device as xcb::ffi::xkb::xcb_xkb_device_spec_t,
+				xcb::xkb::MAP_PART_KEY_ACTIONS as u16, 0,
+				setup.min_keycode(), setup.max_keycode(),
+				0, 0,
+				0, 0, 0,
+				first, counts.len() as u8, actions.len() as u16,
+				0, 0, 0,
+				0, 0, 0,
+				0, 0, 0,
+				0, 0, 0,
+				0, &values)
+		};
+
+		xcb::VoidCookie { cookie, conn: &self.display, checked: true }.request_check()?;
+
+		Ok(())
+	}
+}
+
+impl Drop for Keyboard {
+	fn drop(&mut self) {
+		// Don't leave VT switching disabled if the locker goes away while locked.
+		if let Err(err) = self.block(false) {
+			warn!("could not restore VT switching: {:?}", err);
+		}
+	}
+}
+
+/// The path of the saved actions, `None` if `$XDG_RUNTIME_DIR` is not set.
+fn path() -> Option<PathBuf> {
+	env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("screenruster").join("keymap"))
 }
@@ -22,4 +22,4 @@ mod window;
 pub use self::window::Window;
 
 mod keyboard;
-pub use self::keyboard::Keyboard;
+pub use self::keyboard::{Keyboard, Text};
@@ -52,11 +52,11 @@ impl Preview {
 		let mut saver    = Saver::spawn(name.as_ref())?;
 		let mut throttle = config.saver().throttle();
 
-		saver.config(config.saver().get(name)).unwrap();
-		saver.target(display.name(), window.screen(), window.id() as u64).unwrap();
+		saver.config(config.saver().get(name));
+		saver.target(display.name(), window.screen(), window.id() as u64);
 
 		if throttle {
-			saver.throttle(true).unwrap();
+			saver.throttle(true);
 		}
 
 		let (sender, i_receiver) = channel::unbounded();
@@ -79,7 +79,7 @@ impl Preview {
 					recv(s) -> event => {
 						match event.unwrap() {
 							saver::Response::Forward(api::Response::Initialized) => {
-								saver.start().unwrap();
+								saver.start();
 							}
 
 							saver::Response::Forward(api::Response::Started) => {
@@ -115,7 +115,7 @@ impl Preview {
 
 							xcb::CONFIGURE_NOTIFY => {
 								let event = unsafe { xcb::cast_event::<xcb::ConfigureNotifyEvent>(&event) };
-								saver.resize(event.width() as u32, event.height() as u32).unwrap();
+								saver.resize(event.width() as u32, event.height() as u32);
 							}
 
 							// Handle keyboard input.
@@ -126,42 +126,42 @@ impl Preview {
 									// Toggle throttling.
 									Some(key::t) | Some(key::T) => {
 										throttle = !throttle;
-										saver.throttle(throttle).unwrap();
+										saver.throttle(throttle);
 									}
 
 									// Stop the preview.
 									Some(key::q) | Some(key::Q) => {
-										saver.stop().unwrap();
+										saver.stop();
 									}
 
 									// Test password insertion.
 									Some(key::i) | Some(key::I) => {
-										saver.password(Password::Insert).unwrap();
+										saver.password(Password::Insert);
 									}
 
 									// Test password deletetion.
 									Some(key::d) | Some(key::D) => {
-										saver.password(Password::Delete).unwrap();
+										saver.password(Password::Delete);
 									}
 
 									// Test passsword reset.
 									Some(key::r) | Some(key::R) => {
-										saver.password(Password::Reset).unwrap();
+										saver.password(Password::Reset);
 									}
 
 									// Test password check.
 									Some(key::c) | Some(key::C) => {
-										saver.password(Password::Check).unwrap();
+										saver.password(Password::Check);
 									}
 
 									// Test password success.
 									Some(key::s) | Some(key::S) => {
-										saver.password(Password::Success).unwrap();
+										saver.password(Password::Success);
 									}
 
 									// Test password failure.
 									Some(key::f) | Some(key::F) => {
-										saver.password(Password::Failure).unwrap();
+										saver.password(Password::Failure);
 									}
 
 									_ => ()
@@ -178,7 +178,7 @@ impl Preview {
 
 									button: event.detail(),
 									press:  event.response_type() == xcb::BUTTON_PRESS,
-								}).unwrap();
+								});
 							}
 
 							// Handle mouse motion.
@@ -188,7 +188,7 @@ impl Preview {
 								saver.pointer(Pointer::Move {
 									x: event.event_x() as i32,
 									y: event.event_y() as i32,
-								}).unwrap();
+								});
 							}
 
 							_ => ()
@@ -20,10 +20,10 @@ use std::process::{self, Child, Command, Stdio};
 use std::ops::Deref;
 use std::thread;
 use std::sync::{Arc, Mutex};
-use channel::{self, Receiver, TryRecvError, Sender, SendError};
+use channel::{self, Receiver, TryRecvError, Sender};
 
 use toml;
-use log::{log_enabled};
+use log::{log_enabled, warn};
 use api::{self, json::{self, object}};
 pub use api::{Safety, Password, Pointer};
 
@@ -43,6 +43,7 @@ pub struct Saver {
 
 	started: bool,
 	stopped: bool,
+	broken:  bool,
 }
 
 #[derive(Debug)]
@@ -139,10 +140,16 @@ impl Saver {
 			let mut output = child.lock().unwrap().stdin.take().unwrap();
 
 			thread::spawn(move || {
+				// Whether the process stopped reading, requests are then dropped until
+				// the exit is noticed.
+				let mut broken = false;
+
 				while let Ok(request) = receiver.recv() {
 					match request {
+						Request::Forward(_) if broken => (),
+
 						Request::Forward(request) => {
-							output.write_all(json::stringify(match request {
+							let message = json::stringify(match request {
 								api::Request::Config(config) => object!{
 									"type"   => "config",
 									"config" => config
@@ -221,9 +228,12 @@ impl Saver {
 								api::Request::Stop => object!{
 									"type" => "stop"
 								},
-							}).as_bytes()).unwrap();
+							});
 
-							output.write_all(b"\n").unwrap();
+							if let Err(err) = output.write_all(message.as_bytes()).and_then(|_| output.write_all(b"\n")) {
+								warn!("could not write to saver: {}", err);
+								broken = true;
+							}
 						}
 
 						Request::Exit => {
@@ -258,6 +268,7 @@ impl Saver {
 
 			started: false,
 			stopped: false,
+			broken:  false,
 		})
 	}
 
@@ -271,6 +282,11 @@ impl Saver {
 		self.stopped
 	}
 
+	/// Check if the saver stopped receiving requests.
+	pub fn is_broken(&self) -> bool {
+		self.broken
+	}
+
 	/// Kill the saver process.
 	pub fn kill(&mut self) {
 		let _ = self.process.lock().unwrap().kill();
@@ -301,13 +317,21 @@ impl Saver {
 		}
 	}
 
-	/// Send the API request.
-	fn send(&mut self, request: api::Request) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Forward(request))
+	/// Send the API request, the saver is marked as broken if it's gone and
+	/// the following requests are dropped until its exit is noticed.
+	fn send(&mut self, request: api::Request) {
+		if self.broken {
+			return;
+		}
+
+		if self.sender.send(Request::Forward(request)).is_err() {
+			warn!("could not send to saver, it stopped reading");
+			self.broken = true;
+		}
 	}
 
 	/// Configure the saver.
-	pub fn config(&mut self, config: toml::value::Table) -> Result<(), SendError<Request>> {
+	pub fn config(&mut self, config: toml::value::Table) {
 		fn convert(value: &toml::Value) -> json::JsonValue {
 			match *value {
 				toml::Value::String(ref value) =>
@@ -341,12 +365,12 @@ impl Saver {
 	}
 
 	/// Specify the safety level.
-	pub fn safety(&mut self, level: Safety) -> Result<(), SendError<Request>> {
+	pub fn safety(&mut self, level: Safety) {
 		self.send(api::Request::Safety(level))
 	}
 
 	/// Select the rendering target for the saver.
-	pub fn target<'a, D: Into<Option<&'a str>>>(&mut self, display: D, screen: i32, window: u64) -> Result<(), SendError<Request>> {
+	pub fn target<'a, D: Into<Option<&'a str>>>(&mut self, display: D, screen: i32, window: u64) {
 		self.send(api::Request::Target {
 			display: display.into().map(String::from),
 			screen:  screen,
@@ -355,17 +379,17 @@ impl Saver {
 	}
 
 	/// Throttle or unthrottle the saer.
-	pub fn throttle(&mut self, value: bool) -> Result<(), SendError<Request>> {
+	pub fn throttle(&mut self, value: bool) {
 		self.send(api::Request::Throttle(value))
 	}
 
 	/// Tell the saver the screen has been blanked or unblanked.
-	pub fn blank(&mut self, value: bool) -> Result<(), SendError<Request>> {
+	pub fn blank(&mut self, value: bool) {
 		self.send(api::Request::Blank(value))
 	}
 
 	/// Resize the saver.
-	pub fn resize(&mut self, width: u32, height: u32) -> Result<(), SendError<Request>> {
+	pub fn resize(&mut self, width: u32, height: u32) {
 		self.send(api::Request::Resize {
 			width:  width,
 			height: height,
@@ -373,28 +397,28 @@ impl Saver {
 	}
 
 	/// Send a pointer event.
-	pub fn pointer(&mut self, pointer: Pointer) -> Result<(), SendError<Request>> {
+	pub fn pointer(&mut self, pointer: Pointer) {
 		self.send(api::Request::Pointer(pointer))
 	}
 
 	/// Send a password event.
-	pub fn password(&mut self, password: Password) -> Result<(), SendError<Request>> {
+	pub fn password(&mut self, password: Password) {
 		self.send(api::Request::Password(password))
 	}
 
 	/// Start the saver.
-	pub fn start(&mut self) -> Result<(), SendError<Request>> {
+	pub fn start(&mut self) {
 		self.started = true;
 		self.send(api::Request::Start)
 	}
 
 	/// Start the saver.
-	pub fn lock(&mut self) -> Result<(), SendError<Request>> {
+	pub fn lock(&mut self) {
 		self.send(api::Request::Lock)
 	}
 
 	/// Stop the saver.
-	pub fn stop(&mut self) -> Result<(), SendError<Request>> {
+	pub fn stop(&mut self) {
 		self.stopped = true;
 		self.send(api::Request::Stop)
 	}
@@ -0,0 +1,1205 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+use std::fmt;
+use std::sync::Arc;
+use std::collections::HashSet;
+use std::time::{Instant, SystemTime};
+
+use rand::Rng;
+use log::{info, warn};
+
+use crate::config::{self, Config};
+use crate::timer;
+use crate::locker;
+use crate::auth;
+use crate::interface;
+use crate::lockfile::Reason;
+
+// Timer report IDs.
+const GET_ACTIVE_TIME:       u64 = 1;
+const GET_SESSION_IDLE:      u64 = 2;
+const GET_SESSION_IDLE_TIME: u64 = 3;
+const STATUS:                u64 = 4;
+
+// How many seconds to wait before acting on an Activity after one was
+// already acted upon.
+const ACTIVATION: u64 = 1;
+
+/// The daemon state machine.
+///
+/// It consumes the events coming from the other components and produces the
+/// commands to send to them, it doesn't talk to any of them directly.
+pub struct State {
+	config: Config,
+	clock:  Arc<dyn timer::Clock>,
+
+	locked:    Option<Instant>,
+	graced:    Option<Instant>,
+	started:   Option<Instant>,
+	blanked:   Option<Instant>,
+	suspended: Option<SystemTime>,
+	warned:    bool,
+	dimmed:    bool,
+	battery:   bool,
+	throttled: bool,
+	network:   Vec<String>,
+	grabs:     Option<(bool, bool)>,
+	failure:   Option<String>,
+
+	authenticating: bool,
+
+	inhibitors: HashSet<u32>,
+	throttlers: HashSet<u32>,
+	suspenders: HashSet<u32>,
+
+	commands: Vec<Command>,
+}
+
+/// An event coming from one of the components.
+#[derive(Debug)]
+pub enum Event {
+	Locker(locker::Response),
+	Auth(auth::Response),
+	Interface(interface::Request),
+	Timer(timer::Response),
+}
+
+/// A component that can be restarted.
+#[derive(Copy, Clone, Debug)]
+pub enum Component {
+	Locker,
+	Auth,
+	Interface,
+	Timer,
+}
+
+/// A command to send to one of the components.
+#[derive(Debug)]
+pub enum Command {
+	Locker(locker::Request),
+	Timer(timer::Request),
+	Authenticate(String),
+	Signal(interface::Signal),
+	Response(interface::Response),
+
+	/// Persist the locked state, or remove it.
+	Persist(Option<Reason>),
+
+	/// Run the shell command with the given reason.
+	Hook(String, String),
+}
+
+impl State {
+	/// Create the initial state with the given configuration.
+	pub fn new(config: Config) -> State {
+		State::with_clock(config, Arc::new(timer::System))
+	}
+
+	/// Create the initial state with the given configuration and clock, it
+	/// must be the same clock the timer uses.
+	pub fn with_clock(config: Config, clock: Arc<dyn timer::Clock>) -> State {
+		State {
+			config: config,
+			clock:  clock,
+
+			locked:    None,
+			graced:    None,
+			started:   None,
+			blanked:   None,
+			suspended: None,
+			warned:    false,
+			dimmed:    false,
+			battery:   false,
+			throttled: false,
+			network:   Vec::new(),
+			grabs:     None,
+			failure:   None,
+
+			authenticating: false,
+
+			inhibitors: HashSet::new(),
+			throttlers: HashSet::new(),
+			suspenders: HashSet::new(),
+
+			commands: Vec::new(),
+		}
+	}
+
+	/// Whether the screen is locked.
+	pub fn is_locked(&self) -> bool {
+		self.locked.is_some()
+	}
+
+	/// Lock right away after a restart.
+	pub fn relock(&mut self) -> Vec<Command> {
+		self.start();
+		self.lock(Reason::Restart);
+
+		self.commands.drain(..).collect()
+	}
+
+	/// Bring a restarted component back to the current state, returning the
+	/// commands to execute in order.
+	pub fn restart(&mut self, component: Component) -> Vec<Command> {
+		match component {
+			Component::Locker => {
+				// The new locker starts with the screen on and at full brightness,
+				// and reports the grabs again once locked.
+				self.blanked = None;
+				self.dimmed  = false;
+				self.grabs(None);
+
+				if self.started.is_some() {
+					self.command(Command::Locker(locker::Request::Start));
+				}
+
+				if self.locked.is_some() {
+					self.command(Command::Locker(locker::Request::Lock));
+				}
+
+				if self.throttled {
+					self.command(Command::Locker(locker::Request::Throttle(true)));
+				}
+
+				self.command(Command::Timer(timer::Request::Unblanked));
+			}
+
+			Component::Auth => {
+				// The pending authentication is lost, treat it as a failure.
+				if self.authenticating {
+					self.auth(auth::Response::Failure);
+				}
+			}
+
+			Component::Interface => (),
+
+			Component::Timer => {
+				self.suspended = None;
+
+				if self.started.is_some() {
+					self.command(Command::Timer(timer::Request::Started));
+				}
+
+				if self.locked.is_some() {
+					self.command(Command::Timer(timer::Request::Locked));
+				}
+
+				if self.blanked.is_some() {
+					self.command(Command::Timer(timer::Request::Blanked));
+				}
+			}
+		}
+
+		self.commands.drain(..).collect()
+	}
+
+	/// Handle an event, returning the commands to execute in order.
+	pub fn handle(&mut self, event: Event) -> Vec<Command> {
+		match event {
+			Event::Locker(event) =>
+				self.locker(event),
+
+			Event::Auth(event) =>
+				self.auth(event),
+
+			Event::Interface(event) =>
+				self.interface(event),
+
+			Event::Timer(event) =>
+				self.timer(event),
+		}
+
+		self.commands.drain(..).collect()
+	}
+
+	fn locker(&mut self, event: locker::Response) {
+		match event {
+			// Register timeout.
+			locker::Response::Timeout(what) => {
+				self.command(Command::Timer(timer::Request::Timeout(what)));
+			}
+
+			// On system activity.
+			locker::Response::Activity => {
+				if self.suspended.is_some() {
+					return;
+				}
+
+				// Always reset the blank timer.
+				self.command(Command::Timer(timer::Request::Reset(timer::Event::Blank)));
+
+				if self.blanked.is_some() {
+					self.unblank();
+				}
+
+				self.unwarn();
+				self.undim();
+
+				// If the saver has started but the screen is not locked, or it's
+				// still in the grace period after an automatic lock, unlock it,
+				// otherwise just reset the timers.
+				if let Some(at) = self.started {
+					let grace = self.config.timer().lock_grace().unwrap_or(0) as u64;
+					let grace = self.graced.map_or(false, |at| self.elapsed(at) < grace);
+
+					if (self.locked.is_none() || grace) && self.elapsed(at) >= ACTIVATION {
+						self.stop();
+					}
+				}
+				else {
+					self.command(Command::Timer(timer::Request::Reset(timer::Event::Idle)));
+				}
+			}
+
+			// Try authorization.
+			locker::Response::Password(pwd) => {
+				self.authenticating = true;
+
+				self.command(Command::Signal(interface::Signal::AuthenticationRequest(true)));
+				self.command(Command::Authenticate(pwd));
+			}
+
+			locker::Response::Stopped => {
+				self.stopped();
+			}
+
+			// The locker keeps trying to grab the keyboard, until then the lock is
+			// degraded.
+			locker::Response::LockFailed(reason) => {
+				warn!("lock failed: {}", reason);
+				self.failure = Some(reason.clone());
+
+				if let Some(hook) = self.config.locker().on_lock_failed() {
+					self.command(Command::Hook(hook, reason.clone()));
+				}
+
+				self.command(Command::Signal(interface::Signal::LockFailed(reason)));
+			}
+
+			locker::Response::Safety { keyboard, pointer } => {
+				self.grabs(Some((keyboard, pointer)));
+			}
+
+			// Reconnecting is up to the daemon.
+			locker::Response::Disconnected => (),
+		}
+	}
+
+	fn auth(&mut self, event: auth::Response) {
+		self.authenticating = false;
+
+		match event {
+			auth::Response::Success => {
+				info!("authorization: success");
+
+				self.command(Command::Locker(locker::Request::Auth(true)));
+				self.command(Command::Signal(interface::Signal::AuthenticationRequest(false)));
+				self.stop();
+			}
+
+			auth::Response::Failure => {
+				info!("authorization: failure");
+
+				self.command(Command::Locker(locker::Request::Auth(false)));
+				self.command(Command::Signal(interface::Signal::AuthenticationRequest(false)));
+			}
+		}
+	}
+
+	fn interface(&mut self, event: interface::Request) {
+		match event {
+			interface::Request::Reload(source) => {
+				self.config.reset();
+				let success = self.config.reload(source).is_ok();
+				self.command(Command::Response(interface::Response::Reload(success)));
+
+				self.throttle();
+				self.config.timer().condition("trusted", self.config.policy().is_trusted(&self.network));
+				self.command(Command::Timer(timer::Request::Refresh));
+			}
+
+			interface::Request::Lock => {
+				if self.started.is_none() {
+					self.start();
+				}
+
+				if self.locked.is_none() {
+					self.lock(Reason::Request);
+				}
+				else {
+					// An explicit lock is never undone without authentication.
+					self.graced = None;
+				}
+			}
+
+			// TODO: Implement cycling.
+			interface::Request::Cycle => (),
+
+			interface::Request::SimulateUserActivity => {
+				self.command(Command::Locker(locker::Request::Activity));
+			}
+
+			interface::Request::Inhibit { .. } => {
+				let cookie = insert(&mut self.inhibitors);
+				self.command(Command::Response(interface::Response::Inhibit(cookie)));
+
+				// The saver is not going to start anymore.
+				self.unwarn();
+			}
+
+			interface::Request::UnInhibit(cookie) => {
+				self.inhibitors.remove(&cookie);
+			}
+
+			interface::Request::Throttle { .. } => {
+				let cookie = insert(&mut self.throttlers);
+				self.command(Command::Response(interface::Response::Throttle(cookie)));
+				self.throttle();
+			}
+
+			interface::Request::UnThrottle(cookie) => {
+				if self.throttlers.remove(&cookie) {
+					self.throttle();
+				}
+			}
+
+			interface::Request::OnBattery(value) => {
+				self.battery = value;
+				self.config.timer().condition("battery", value);
+				self.command(Command::Timer(timer::Request::Refresh));
+				self.throttle();
+			}
+
+			interface::Request::Network(ids) => {
+				self.config.timer().condition("trusted", self.config.policy().is_trusted(&ids));
+				self.network = ids;
+				self.command(Command::Timer(timer::Request::Refresh));
+			}
+
+			interface::Request::SetActive(active) => {
+				if active {
+					if self.started.is_none() {
+						self.start();
+					}
+				}
+				else {
+					if self.started.is_some() && self.locked.is_none() {
+						self.stop();
+					}
+				}
+			}
+
+			interface::Request::GetActive => {
+				let active = self.started.is_some();
+				self.command(Command::Response(interface::Response::Active(active)));
+			}
+
+			interface::Request::GetActiveTime => {
+				self.command(Command::Timer(timer::Request::Report { id: GET_ACTIVE_TIME }));
+			}
+
+			interface::Request::GetSessionIdle => {
+				self.command(Command::Timer(timer::Request::Report { id: GET_SESSION_IDLE }));
+			}
+
+			interface::Request::GetSessionIdleTime => {
+				self.command(Command::Timer(timer::Request::Report { id: GET_SESSION_IDLE_TIME }));
+			}
+
+			interface::Request::Status => {
+				self.command(Command::Timer(timer::Request::Report { id: STATUS }));
+			}
+
+			interface::Request::GetSafety => {
+				let safety = self.safety().into();
+				self.command(Command::Response(interface::Response::Safety(safety)));
+			}
+
+			interface::Request::Suspend { .. } => {
+				self.suspend(self.clock.system());
+
+				let cookie = insert(&mut self.suspenders);
+				self.command(Command::Response(interface::Response::Suspend(cookie)));
+			}
+
+			interface::Request::Resume(cookie) => {
+				if self.suspenders.remove(&cookie) {
+					self.resume();
+				}
+			}
+
+			interface::Request::PrepareForSleep(time) => {
+				if let Some(time) = time {
+					match self.config.locker().on_suspend() {
+						config::OnSuspend::Ignore |
+						config::OnSuspend::Activate |
+						config::OnSuspend::Lock => (),
+
+						config::OnSuspend::UseSystemTime => {
+							self.suspend(time);
+						}
+					}
+				}
+				else {
+					match self.config.locker().on_suspend() {
+						config::OnSuspend::Ignore => (),
+
+						config::OnSuspend::UseSystemTime => {
+							self.resume();
+						}
+
+						config::OnSuspend::Activate => {
+							self.start();
+						}
+
+						config::OnSuspend::Lock => {
+							self.start();
+							self.lock(Reason::Suspend);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	fn timer(&mut self, event: timer::Response) {
+		match event {
+			timer::Response::Report { id: GET_ACTIVE_TIME, started, .. } => {
+				let time = started.map_or(0, |i| self.elapsed(i));
+				self.command(Command::Response(interface::Response::ActiveTime(time)));
+			}
+
+			timer::Response::Report { id: GET_SESSION_IDLE, idle, .. } => {
+				let value = self.elapsed(idle) >= 5;
+				self.command(Command::Response(interface::Response::SessionIdle(value)));
+			}
+
+			timer::Response::Report { id: GET_SESSION_IDLE_TIME, idle, .. } => {
+				let time = self.elapsed(idle);
+				self.command(Command::Response(interface::Response::SessionIdleTime(time)));
+			}
+
+			timer::Response::Report { id: STATUS, idle, .. } => {
+				let status = self.status(idle);
+				self.command(Command::Response(interface::Response::Status(status)));
+			}
+
+			timer::Response::Report { .. } => {
+				unreachable!();
+			}
+
+			timer::Response::Timeout { id } => {
+				self.command(Command::Locker(locker::Request::Timeout { id }));
+			}
+
+			timer::Response::Suspended(time) => {
+				self.suspended = Some(time);
+			}
+
+			timer::Response::Resumed => {
+				self.suspended = None;
+			}
+
+			timer::Response::Heartbeat(idle) => {
+				self.command(Command::Locker(locker::Request::Sanitize));
+				self.command(Command::Signal(interface::Signal::SessionIdle(self.elapsed(idle) > 5)));
+			}
+
+			timer::Response::Warn(seconds) => {
+				if self.inhibitors.is_empty() && self.started.is_none() {
+					self.warn(seconds);
+				}
+			}
+
+			timer::Response::Dim => {
+				if self.inhibitors.is_empty() && self.started.is_none() {
+					self.dim();
+				}
+			}
+
+			timer::Response::Start => {
+				if self.inhibitors.is_empty() {
+					self.start();
+				}
+				else {
+					self.command(Command::Timer(timer::Request::Stopped));
+				}
+			}
+
+			timer::Response::Lock => {
+				// The timer could be late for a lock that was already requested.
+				if self.locked.is_none() {
+					self.lock(Reason::Timer);
+
+					// Automatic locks can be undone without authentication for a
+					// while.
+					self.graced = Some(self.clock.now());
+				}
+			}
+
+			timer::Response::Power(stage) => {
+				if self.inhibitors.is_empty() {
+					self.stage(stage);
+				}
+				else {
+					self.command(Command::Timer(timer::Request::Unblanked));
+				}
+			}
+
+			timer::Response::Blank => {
+				if self.inhibitors.is_empty() {
+					self.blank();
+				}
+				else {
+					self.command(Command::Timer(timer::Request::Unblanked));
+				}
+			}
+		}
+	}
+
+	/// The status as ordered key-value pairs.
+	fn status(&self, idle: Instant) -> Vec<(String, String)> {
+		fn seconds(value: Option<u32>) -> String {
+			value.map_or("never".into(), |v| v.to_string())
+		}
+
+		let mut profiles = self.config.timer().profiles();
+		for name in self.config.locker().profiles() {
+			if !profiles.contains(&name) {
+				profiles.push(name);
+			}
+		}
+
+		let locked = match (self.locked, &self.failure) {
+			(Some(_), Some(_)) => "degraded".into(),
+			(locked, _)        => locked.is_some().to_string(),
+		};
+
+		vec![
+			("active".into(),    self.started.is_some().to_string()),
+			("locked".into(),    locked),
+			("failure".into(),   self.failure.clone().unwrap_or("none".into())),
+			("blanked".into(),   self.blanked.is_some().to_string()),
+			("idle".into(),      self.elapsed(idle).to_string()),
+			("inhibited".into(), (!self.inhibitors.is_empty()).to_string()),
+			("throttled".into(), self.throttled.to_string()),
+			("battery".into(),   self.battery.to_string()),
+			("safety".into(),    self.safety().into()),
+			("trusted".into(),   self.config.policy().is_trusted(&self.network).to_string()),
+			("profiles".into(),  if profiles.is_empty() { "none".into() } else { profiles.join(", ") }),
+			("timeout".into(),   self.config.timer().timeout().to_string()),
+			("lock".into(),      seconds(self.config.timer().lock())),
+			("blank".into(),     seconds(self.config.timer().blank())),
+			("dim".into(),       seconds(self.config.timer().dim())),
+			("warn".into(),      seconds(self.config.timer().warn())),
+		]
+	}
+
+	/// The safety level of the lock, derived from the grabbed inputs.
+	fn safety(&self) -> &'static str {
+		match self.grabs {
+			Some((true, true))  => "high",
+			Some((true, false)) => "medium",
+			Some(_)             => "low",
+			None                => "none",
+		}
+	}
+
+	fn grabs(&mut self, value: Option<(bool, bool)>) {
+		let previous = self.safety();
+		self.grabs = value;
+
+		// The lock is not degraded anymore once the keyboard is grabbed.
+		if let Some((true, _)) = value {
+			if self.failure.take().is_some() {
+				info!("lock recovered");
+			}
+		}
+
+		if self.safety() != previous {
+			self.command(Command::Signal(interface::Signal::SafetyChanged(self.safety().into())));
+		}
+	}
+
+	/// Seconds since the given instant.
+	fn elapsed(&self, at: Instant) -> u64 {
+		self.clock.now().saturating_duration_since(at).as_secs()
+	}
+
+	fn command(&mut self, command: Command) {
+		self.commands.push(command);
+	}
+
+	fn throttle(&mut self) {
+		let value = !self.throttlers.is_empty() ||
+			(self.battery && self.config.saver().throttle_on_battery()) ||
+			self.config.saver().throttle();
+
+		if value != self.throttled {
+			self.throttled = value;
+			self.command(Command::Locker(locker::Request::Throttle(value)));
+		}
+	}
+
+	fn suspend(&mut self, time: SystemTime) {
+		if self.suspenders.is_empty() && self.suspended.is_none() {
+			self.command(Command::Timer(timer::Request::Suspend(time)));
+		}
+	}
+
+	fn resume(&mut self) {
+		if self.suspenders.is_empty() && self.suspended.is_some() {
+			if self.blanked.is_some() {
+				self.unblank();
+			}
+
+			self.command(Command::Timer(timer::Request::Resume));
+		}
+	}
+
+	fn blank(&mut self) {
+		self.blanked = Some(self.clock.now());
+
+		self.command(Command::Locker(locker::Request::Power(false)));
+		self.command(Command::Timer(timer::Request::Blanked));
+	}
+
+	fn stage(&mut self, stage: timer::Power) {
+		if self.blanked.is_none() {
+			self.blanked = Some(self.clock.now());
+		}
+
+		self.command(Command::Locker(locker::Request::Stage(stage)));
+	}
+
+	fn unblank(&mut self) {
+		self.blanked = None;
+
+		self.command(Command::Locker(locker::Request::Power(true)));
+		self.command(Command::Timer(timer::Request::Unblanked));
+	}
+
+	fn warn(&mut self, seconds: u64) {
+		self.warned = true;
+
+		self.command(Command::Signal(interface::Signal::IdleWarning(Some(seconds))));
+	}
+
+	fn unwarn(&mut self) {
+		if self.warned {
+			self.warned = false;
+
+			self.command(Command::Signal(interface::Signal::IdleWarning(None)));
+		}
+	}
+
+	fn dim(&mut self) {
+		self.dimmed = true;
+
+		self.command(Command::Locker(locker::Request::Dim(true)));
+	}
+
+	fn undim(&mut self) {
+		if self.dimmed {
+			self.dimmed = false;
+
+			self.command(Command::Locker(locker::Request::Dim(false)));
+		}
+	}
+
+	fn start(&mut self) {
+		self.unwarn();
+
+		// The locker restores the brightness when starting.
+		self.dimmed = false;
+
+		self.started = Some(self.clock.now());
+
+		self.command(Command::Locker(locker::Request::Start));
+		self.command(Command::Signal(interface::Signal::Active(true)));
+		self.command(Command::Timer(timer::Request::Started));
+	}
+
+	fn lock(&mut self, reason: Reason) {
+		self.locked = Some(self.clock.now());
+		self.graced = None;
+
+		self.command(Command::Persist(Some(reason)));
+		self.command(Command::Locker(locker::Request::Lock));
+		self.command(Command::Timer(timer::Request::Locked));
+	}
+
+	fn stop(&mut self) {
+		self.command(Command::Locker(locker::Request::Stop));
+	}
+
+	fn stopped(&mut self) {
+		// The lock is over, either authenticated or during the grace period.
+		if self.locked.is_some() {
+			self.command(Command::Persist(None));
+		}
+
+		self.started = None;
+		self.locked  = None;
+		self.graced  = None;
+		self.failure = None;
+		self.grabs(None);
+
+		self.command(Command::Signal(interface::Signal::Active(false)));
+		self.command(Command::Timer(timer::Request::Stopped));
+	}
+}
+
+impl fmt::Debug for State {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let since = |value: Option<Instant>| value.map(|i| self.elapsed(i));
+
+		f.debug_struct("State")
+			.field("started", &since(self.started))
+			.field("locked", &since(self.locked))
+			.field("graced", &since(self.graced))
+			.field("blanked", &since(self.blanked))
+			.field("suspended", &self.suspended)
+			.field("warned", &self.warned)
+			.field("dimmed", &self.dimmed)
+			.field("battery", &self.battery)
+			.field("throttled", &self.throttled)
+			.field("network", &self.network)
+			.field("safety", &self.safety())
+			.field("failure", &self.failure)
+			.field("authenticating", &self.authenticating)
+			.field("inhibitors", &self.inhibitors.len())
+			.field("throttlers", &self.throttlers.len())
+			.field("suspenders", &self.suspenders.len())
+			.finish()
+	}
+}
+
+/// Insert a new random cookie in the set.
+fn insert(set: &mut HashSet<u32>) -> u32 {
+	loop {
+		let cookie = rand::thread_rng().gen();
+
+		if set.contains(&cookie) {
+			continue;
+		}
+
+		set.insert(cookie);
+
+		return cookie;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs;
+	use std::process;
+	use std::sync::Arc;
+	use std::time::Duration;
+
+	use crate::config::Config;
+	use crate::timer::{self, Clock, Manual};
+	use crate::locker;
+	use crate::auth;
+	use crate::interface;
+	use crate::lockfile::Reason;
+	use super::{State, Event, Command};
+
+	/// Create a state on a manual clock with the given configuration.
+	fn state(source: &str) -> (State, Arc<Manual>) {
+		let path = env::temp_dir().join(format!("screenruster-{}-{:x}.toml",
+			process::id(), rand::random::<u64>()));
+
+		fs::write(&path, source).unwrap();
+		let config = Config::load(Some(&path)).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		let clock = Arc::new(Manual::new());
+		let state = State::with_clock(config, clock.clone());
+
+		(state, clock)
+	}
+
+	fn interface(state: &mut State, request: interface::Request) -> Vec<Command> {
+		state.handle(Event::Interface(request))
+	}
+
+	fn locker(state: &mut State, response: locker::Response) -> Vec<Command> {
+		state.handle(Event::Locker(response))
+	}
+
+	fn timer(state: &mut State, response: timer::Response) -> Vec<Command> {
+		state.handle(Event::Timer(response))
+	}
+
+	#[test]
+	fn lock() {
+		let (mut state, _) = state("");
+
+		assert!(matches!(interface(&mut state, interface::Request::Lock)[..], [
+			Command::Locker(locker::Request::Start),
+			Command::Signal(interface::Signal::Active(true)),
+			Command::Timer(timer::Request::Started),
+			Command::Persist(Some(Reason::Request)),
+			Command::Locker(locker::Request::Lock),
+			Command::Timer(timer::Request::Locked),
+		]));
+
+		assert!(state.is_locked());
+
+		// Locking again does nothing.
+		assert!(matches!(interface(&mut state, interface::Request::Lock)[..], []));
+	}
+
+	#[test]
+	fn lock_while_blanked() {
+		let (mut state, clock) = state("");
+
+		assert!(matches!(timer(&mut state, timer::Response::Blank)[..], [
+			Command::Locker(locker::Request::Power(false)),
+			Command::Timer(timer::Request::Blanked),
+		]));
+
+		// The screen stays blank.
+		assert!(matches!(interface(&mut state, interface::Request::Lock)[..], [
+			Command::Locker(locker::Request::Start),
+			Command::Signal(interface::Signal::Active(true)),
+			Command::Timer(timer::Request::Started),
+			Command::Persist(Some(Reason::Request)),
+			Command::Locker(locker::Request::Lock),
+			Command::Timer(timer::Request::Locked),
+		]));
+
+		// Activity turns the screen back on without unlocking.
+		clock.advance(Duration::from_secs(10));
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+			Command::Locker(locker::Request::Power(true)),
+			Command::Timer(timer::Request::Unblanked),
+		]));
+
+		assert!(state.is_locked());
+	}
+
+	#[test]
+	fn set_inactive_while_locked() {
+		let (mut state, clock) = state("");
+
+		interface(&mut state, interface::Request::Lock);
+		clock.advance(Duration::from_secs(10));
+
+		assert!(matches!(interface(&mut state, interface::Request::SetActive(false))[..], []));
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+		]));
+
+		assert!(state.is_locked());
+	}
+
+	#[test]
+	fn set_inactive() {
+		let (mut state, _) = state("");
+
+		assert!(matches!(interface(&mut state, interface::Request::SetActive(true))[..], [
+			Command::Locker(locker::Request::Start),
+			Command::Signal(interface::Signal::Active(true)),
+			Command::Timer(timer::Request::Started),
+		]));
+
+		assert!(matches!(interface(&mut state, interface::Request::SetActive(false))[..], [
+			Command::Locker(locker::Request::Stop),
+		]));
+
+		assert!(matches!(locker(&mut state, locker::Response::Stopped)[..], [
+			Command::Signal(interface::Signal::Active(false)),
+			Command::Timer(timer::Request::Stopped),
+		]));
+	}
+
+	#[test]
+	fn activity() {
+		let (mut state, clock) = state("");
+
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+			Command::Timer(timer::Request::Reset(timer::Event::Idle)),
+		]));
+
+		timer(&mut state, timer::Response::Start);
+
+		// Activity right after starting is ignored.
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+		]));
+
+		clock.advance(Duration::from_secs(1));
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+			Command::Locker(locker::Request::Stop),
+		]));
+	}
+
+	#[test]
+	fn activity_during_suspend() {
+		let (mut state, clock) = state("");
+
+		let commands = interface(&mut state, interface::Request::Suspend {
+			application: "test".into(),
+			reason:      "test".into(),
+		});
+
+		let (time, cookie) = match commands[..] {
+			[Command::Timer(timer::Request::Suspend(time)), Command::Response(interface::Response::Suspend(cookie))] =>
+				(time, cookie),
+
+			_ =>
+				panic!("unexpected commands: {:?}", commands)
+		};
+
+		assert!(matches!(timer(&mut state, timer::Response::Suspended(time))[..], []));
+
+		clock.advance(Duration::from_secs(10));
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], []));
+
+		assert!(matches!(interface(&mut state, interface::Request::Resume(cookie))[..], [
+			Command::Timer(timer::Request::Resume),
+		]));
+
+		// Activity is ignored until the timers are resumed.
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], []));
+		assert!(matches!(timer(&mut state, timer::Response::Resumed)[..], []));
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+			Command::Timer(timer::Request::Reset(timer::Event::Idle)),
+		]));
+	}
+
+	#[test]
+	fn inhibited() {
+		let (mut state, _) = state("");
+
+		interface(&mut state, interface::Request::Inhibit {
+			application: "test".into(),
+			reason:      "test".into(),
+		});
+
+		assert!(matches!(timer(&mut state, timer::Response::Start)[..], [
+			Command::Timer(timer::Request::Stopped),
+		]));
+
+		assert!(matches!(timer(&mut state, timer::Response::Blank)[..], [
+			Command::Timer(timer::Request::Unblanked),
+		]));
+	}
+
+	#[test]
+	fn grace() {
+		let (mut state, clock) = state("[timer]\nlock-grace = 10");
+
+		timer(&mut state, timer::Response::Start);
+		timer(&mut state, timer::Response::Lock);
+		clock.advance(Duration::from_secs(5));
+
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+			Command::Locker(locker::Request::Stop),
+		]));
+
+		assert!(matches!(locker(&mut state, locker::Response::Stopped)[..], [
+			Command::Persist(None),
+			Command::Signal(interface::Signal::Active(false)),
+			Command::Timer(timer::Request::Stopped),
+		]));
+
+		// Past the grace period.
+		timer(&mut state, timer::Response::Start);
+		timer(&mut state, timer::Response::Lock);
+		clock.advance(Duration::from_secs(10));
+
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+		]));
+	}
+
+	#[test]
+	fn grace_after_request() {
+		let (mut state, clock) = state("[timer]\nlock-grace = 10");
+
+		// Requesting a lock during the grace period ends it.
+		timer(&mut state, timer::Response::Start);
+		timer(&mut state, timer::Response::Lock);
+		assert!(matches!(interface(&mut state, interface::Request::Lock)[..], []));
+		clock.advance(Duration::from_secs(5));
+
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+		]));
+
+		// A late timer lock doesn't start one.
+		timer(&mut state, timer::Response::Lock);
+		clock.advance(Duration::from_secs(1));
+
+		assert!(matches!(locker(&mut state, locker::Response::Activity)[..], [
+			Command::Timer(timer::Request::Reset(timer::Event::Blank)),
+		]));
+
+		assert!(state.is_locked());
+	}
+
+	#[test]
+	fn inhibited_while_warned() {
+		let (mut state, _) = state("");
+
+		assert!(matches!(timer(&mut state, timer::Response::Warn(10))[..], [
+			Command::Signal(interface::Signal::IdleWarning(Some(10))),
+		]));
+
+		assert!(matches!(interface(&mut state, interface::Request::Inhibit {
+			application: "test".into(),
+			reason:      "test".into(),
+		})[..], [
+			Command::Response(interface::Response::Inhibit(_)),
+			Command::Signal(interface::Signal::IdleWarning(None)),
+		]));
+
+		assert!(matches!(timer(&mut state, timer::Response::Warn(9))[..], []));
+	}
+
+	/// Get the status through a timer report.
+	fn status(state: &mut State, clock: &Manual) -> Vec<(String, String)> {
+		assert!(matches!(interface(state, interface::Request::Status)[..], [
+			Command::Timer(timer::Request::Report { id: super::STATUS }),
+		]));
+
+		let commands = timer(state, timer::Response::Report {
+			id:         super::STATUS,
+			beat:       clock.now(),
+			idle:       clock.now(),
+			started:    None,
+			locked:     None,
+			dimmed:     None,
+			blanked:    None,
+			unblanked:  None,
+			powered:    None,
+			suspended:  None,
+			correction: 0,
+			corrected:  true,
+			timeouts:   Default::default(),
+		});
+
+		match commands.into_iter().next() {
+			Some(Command::Response(interface::Response::Status(status))) =>
+				status,
+
+			command =>
+				panic!("unexpected command: {:?}", command)
+		}
+	}
+
+	fn get<'a>(status: &'a [(String, String)], key: &str) -> &'a str {
+		&status.iter().find(|(k, _)| k == key).unwrap().1
+	}
+
+	#[test]
+	fn lock_failed() {
+		let (mut state, clock) = state("");
+
+		interface(&mut state, interface::Request::Lock);
+		assert_eq!(get(&status(&mut state, &clock), "locked"), "true");
+
+		assert!(matches!(locker(&mut state, locker::Response::Safety { keyboard: false, pointer: true })[..], [
+			Command::Signal(interface::Signal::SafetyChanged(_)),
+		]));
+
+		assert!(matches!(locker(&mut state, locker::Response::LockFailed("could not grab the keyboard".into()))[..], [
+			Command::Signal(interface::Signal::LockFailed(_)),
+		]));
+
+		let current = status(&mut state, &clock);
+		assert_eq!(get(&current, "locked"), "degraded");
+		assert_eq!(get(&current, "failure"), "could not grab the keyboard");
+		assert_eq!(get(&current, "safety"), "low");
+
+		// The locker managed to grab the keyboard later on.
+		locker(&mut state, locker::Response::Safety { keyboard: true, pointer: true });
+
+		let current = status(&mut state, &clock);
+		assert_eq!(get(&current, "locked"), "true");
+		assert_eq!(get(&current, "failure"), "none");
+		assert_eq!(get(&current, "safety"), "high");
+		assert!(state.is_locked());
+	}
+
+	#[test]
+	fn authentication() {
+		let (mut state, _) = state("");
+
+		interface(&mut state, interface::Request::Lock);
+
+		assert!(matches!(locker(&mut state, locker::Response::Password("password".into()))[..], [
+			Command::Signal(interface::Signal::AuthenticationRequest(true)),
+			Command::Authenticate(_),
+		]));
+
+		assert!(matches!(state.handle(Event::Auth(auth::Response::Failure))[..], [
+			Command::Locker(locker::Request::Auth(false)),
+			Command::Signal(interface::Signal::AuthenticationRequest(false)),
+		]));
+
+		assert!(state.is_locked());
+
+		locker(&mut state, locker::Response::Password("password".into()));
+		assert!(matches!(state.handle(Event::Auth(auth::Response::Success))[..], [
+			Command::Locker(locker::Request::Auth(true)),
+			Command::Signal(interface::Signal::AuthenticationRequest(false)),
+			Command::Locker(locker::Request::Stop),
+		]));
+
+		assert!(matches!(locker(&mut state, locker::Response::Stopped)[..], [
+			Command::Persist(None),
+			Command::Signal(interface::Signal::Active(false)),
+			Command::Timer(timer::Request::Stopped),
+		]));
+
+		assert!(!state.is_locked());
+	}
+
+	#[test]
+	fn trusted_network() {
+		let (mut state, clock) = state(r#"
+			[policy.network]
+			trusted = ["7d5b6a1e-4c43-4a57-9a3e-0d3b9a2f1c11", "CorpWiFi@00:11:22:33:44:55"]
+		"#);
+
+		// A network with the same name isn't enough, the timers are checked
+		// again either way.
+		assert!(matches!(interface(&mut state, interface::Request::Network(vec!["CorpWiFi".into()]))[..], [
+			Command::Timer(timer::Request::Refresh),
+		]));
+		assert_eq!(get(&status(&mut state, &clock), "trusted"), "false");
+
+		interface(&mut state, interface::Request::Network(vec!["CorpWiFi@66:77:88:99:AA:BB".into()]));
+		assert_eq!(get(&status(&mut state, &clock), "trusted"), "false");
+
+		interface(&mut state, interface::Request::Network(vec!["CorpWiFi@00:11:22:33:44:55".into()]));
+		assert_eq!(get(&status(&mut state, &clock), "trusted"), "true");
+
+		interface(&mut state, interface::Request::Network(vec!["7d5b6a1e-4c43-4a57-9a3e-0d3b9a2f1c11".into()]));
+		assert_eq!(get(&status(&mut state, &clock), "trusted"), "true");
+	}
+}
@@ -17,17 +17,20 @@
 
 use std::collections::{HashMap, HashSet};
 use std::thread;
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
 use std::ops::Deref;
 use std::time::{Instant, SystemTime, Duration};
-use channel::{self, Receiver, Sender, SendError};
+use channel::{self, Receiver, Sender, SendError, RecvTimeoutError};
 
 use crate::error;
 use crate::config;
 
 /// The timer manager.
 ///
-/// It manages timers with a 1 second granularity, reporting timer expirations
-/// through a channel.
+/// It manages timers sleeping until the next deadline, reporting timer
+/// expirations through a channel and answering requests right away.
 ///
 /// It also handles custom timeouts on request.
 pub struct Timer {
@@ -68,6 +71,14 @@ pub enum Request {
 
 	/// The screen saver was stopped, restarts all timers.
 	Stopped,
+
+	/// The configuration or its runtime conditions changed, check the timers
+	/// again.
+	Refresh,
+
+	/// Check the timers again, used after advancing a manual clock.
+	#[cfg(test)]
+	Step,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -93,6 +104,14 @@ pub enum Event {
 	Blank,
 }
 
+/// The DPMS power saving stages.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+pub enum Power {
+	Standby,
+	Suspend,
+	Off,
+}
+
 #[derive(Clone, Debug)]
 pub enum Response {
 	/// Report various information about the timer internal status.
@@ -102,8 +121,10 @@ pub enum Response {
 		idle:       Instant,
 		started:    Option<Instant>,
 		locked:     Option<Instant>,
+		dimmed:     Option<Instant>,
 		blanked:    Option<Instant>,
 		unblanked:  Option<Instant>,
+		powered:    Option<Power>,
 		suspended:  Option<SystemTime>,
 		correction: u64,
 		corrected:  bool,
@@ -124,6 +145,12 @@ pub enum Response {
 	/// Hurts my kokoro.
 	Heartbeat(Instant),
 
+	/// The screen saver is going to start in the given seconds.
+	Warn(u64),
+
+	/// The system has been idle long enough to dim.
+	Dim,
+
 	/// The system has been idle long enough.
 	Start,
 
@@ -132,20 +159,28 @@ pub enum Response {
 
 	/// The system has been idle long enough to blank.
 	Blank,
+
+	/// The system has been idle long enough to enter a power saving stage.
+	Power(Power),
 }
 
 impl Timer {
 	/// Spawn the timer thread with the given configuration.
-	pub fn spawn(config: config::Timer) -> error::Result<Timer> {
+	pub fn spawn(config: config::Timer, power: config::Power) -> error::Result<Timer> {
+		Timer::with_clock(config, power, Arc::new(System))
+	}
+
+	/// Spawn the timer thread with the given configuration and clock.
+	pub fn with_clock(config: config::Timer, power: config::Power, clock: Arc<dyn Clock>) -> error::Result<Timer> {
 		let (sender, i_receiver) = channel::unbounded();
 		let (i_sender, receiver) = channel::unbounded();
 
 		thread::spawn(move || {
 			// Instant to check last heartbeat.
-			let mut beat = Instant::now();
+			let mut beat = clock.now();
 
 			// Instant to check last activity time.
-			let mut idle = Instant::now();
+			let mut idle = clock.now();
 
 			// Instant to check when the screen saver starter.
 			let mut started = None::<Instant>;
@@ -153,12 +188,18 @@ impl Timer {
 			// Instant to check when the screen was locked.
 			let mut locked = None::<Instant>;
 
+			// Instant to check when the screen was dimmed.
+			let mut dimmed = None::<Instant>;
+
 			// Instant to check when the screen was blanked.
 			let mut blanked = None::<Instant>;
 
 			// Instant to check when the screen was unblanked.
 			let mut unblanked = None::<Instant>;
 
+			// The current power saving stage.
+			let mut powered = None::<Power>;
+
 			// Instant to check when the timer was suspended.
 			let mut suspended = None::<SystemTime>;
 
@@ -171,11 +212,185 @@ impl Timer {
 			// The registered timeouts.
 			let mut timeouts = HashMap::<u64, (Instant, u64)>::new();
 
+			// The seconds left before starting that were last warned about.
+			let mut warned = None::<u64>;
+
 			loop {
-				thread::sleep(Duration::from_secs(1));
+				let now = clock.now();
+
+				// The next instant something is due, waking up at least for every
+				// heart beat.
+				let mut next = beat + Duration::from_secs(config.beat() as u64);
+
+				// The active profiles may change at the next schedule boundary.
+				if let Some(seconds) = config.boundary() {
+					next = next.min(now + Duration::from_secs(seconds));
+				}
+
+				// Check if the instant is due, otherwise consider it for the next
+				// wake up.
+				macro_rules! due {
+					($at:expr) => ({
+						let at = $at;
+
+						if at > now {
+							next = next.min(at);
+							false
+						}
+						else {
+							true
+						}
+					});
+				}
+
+				// The instant after the given seconds of inactivity, accounting for
+				// the time spent suspended.
+				let after = |from: Instant, seconds: u64| {
+					(from + Duration::from_secs(seconds)).checked_sub(Duration::from_secs(correction)).unwrap_or(from)
+				};
 
-				while let Ok(request) = receiver.try_recv() {
-					match request {
+				// Handle custom timeouts.
+				{
+					let mut expired = HashSet::new();
+
+					for (&id, &(started, seconds)) in &timeouts {
+						if due!(started + Duration::from_secs(seconds)) {
+							expired.insert(id);
+						}
+					}
+
+					for &id in &expired {
+						sender.send(Response::Timeout { id }).unwrap();
+						timeouts.remove(&id);
+					}
+				}
+
+				// If it's time to send a heart beat, send one and reset.
+				if due!(beat + Duration::from_secs(config.beat() as u64)) {
+					beat = now;
+					sender.send(Response::Heartbeat(idle)).unwrap();
+				}
+
+				// Do not check events if the timers are suspended.
+				if suspended.is_none() {
+					// If blanking is enabled and the screen is not already blanked, using
+					// the locked timeout if the screen is locked.
+					let blank = if locked.is_some() {
+						config.locked_blank().or(config.blank())
+					}
+					else {
+						config.blank()
+					};
+
+					if let (Some(seconds), false) = (blank, blanked.is_some()) {
+						if due!(unblanked.unwrap_or(idle) + Duration::from_secs(seconds as u64)) {
+							sender.send(Response::Blank).unwrap();
+							blanked = Some(now);
+						}
+					}
+
+					// Go through the power saving stages, using the locked timeouts if
+					// the screen is locked.
+					{
+						let base      = unblanked.unwrap_or(idle);
+						let mut stage = None;
+
+						for &(current, seconds) in &[
+							(Power::Standby, power.standby(locked.is_some())),
+							(Power::Suspend, power.suspend(locked.is_some())),
+							(Power::Off, power.off(locked.is_some())),
+						] {
+							if let Some(seconds) = seconds {
+								if powered.map_or(true, |powered| current > powered) && due!(base + Duration::from_secs(seconds as u64)) {
+									stage = Some(current);
+								}
+							}
+						}
+
+						if let Some(stage) = stage {
+							sender.send(Response::Power(stage)).unwrap();
+							powered = Some(stage);
+						}
+					}
+
+					// If warning is enabled and the screen saver is about to start, send
+					// the seconds left whenever they change.
+					if let (Some(before), false) = (config.warn(), started.is_some()) {
+						let start = after(idle, config.timeout() as u64);
+
+						if start > now {
+							let left = start - now;
+							let left = left.as_secs() + if left.subsec_nanos() > 0 { 1 } else { 0 };
+
+							if left <= before as u64 {
+								if warned != Some(left) {
+									sender.send(Response::Warn(left)).unwrap();
+									warned = Some(left);
+								}
+
+								due!(start - Duration::from_secs(left - 1));
+							}
+							else {
+								due!(start - Duration::from_secs(before as u64));
+							}
+						}
+					}
+
+					// If dimming is enabled, the screen saver has not started and the
+					// screen is not already dimmed.
+					if let (Some(seconds), false, false) = (config.dim(), started.is_some(), dimmed.is_some()) {
+						if due!(after(idle, seconds as u64)) {
+							sender.send(Response::Dim).unwrap();
+							dimmed = Some(now);
+						}
+					}
+
+					// If the system has been idle long enough send the message.
+					if started.is_none() && due!(after(idle, config.timeout() as u64)) {
+						sender.send(Response::Start).unwrap();
+						started = Some(now);
+					}
+
+					// If the screen saver has been started, the screen is not locked and locking is enabled.
+					if let (Some(start), Some(seconds), false) = (started, config.lock(), locked.is_some()) {
+						if due!(after(start, seconds as u64)) {
+							sender.send(Response::Lock).unwrap();
+							locked = Some(now);
+						}
+					}
+
+					// Only resume after one corrected check, this avoids activities right
+					// after resume cancelling timer events.
+					if !corrected {
+						sender.send(Response::Resumed).unwrap();
+						corrected = true;
+					}
+				}
+
+				// Wait for the next deadline, handling requests as soon as they come.
+				let mut request = match clock.wait(next) {
+					Some(timeout) => match receiver.recv_timeout(timeout) {
+						Ok(request) =>
+							Some(request),
+
+						Err(RecvTimeoutError::Timeout) =>
+							None,
+
+						Err(RecvTimeoutError::Disconnected) =>
+							break,
+					},
+
+					None => match receiver.recv() {
+						Ok(request) =>
+							Some(request),
+
+						Err(_) =>
+							break,
+					},
+				};
+
+				while let Some(current) = request.take() {
+					match current {
 						Request::Report { id } => {
 							sender.send(Response::Report {
 								id:         id,
@@ -183,8 +398,10 @@ impl Timer {
 								idle:       idle,
 								started:    started,
 								locked:     locked,
+								dimmed:     dimmed,
 								blanked:    blanked,
 								unblanked:  unblanked,
+								powered:    powered,
 								suspended:  suspended,
 								correction: correction,
 								corrected:  corrected,
@@ -193,7 +410,7 @@ impl Timer {
 						}
 
 						Request::Timeout(Timeout::Set { id, seconds }) => {
-							timeouts.insert(id, (Instant::now(), seconds));
+							timeouts.insert(id, (clock.now(), seconds));
 						}
 
 						Request::Timeout(Timeout::Cancel { id }) => {
@@ -201,13 +418,16 @@ impl Timer {
 						}
 
 						Request::Reset(Event::Idle) => {
-							idle       = Instant::now();
+							idle       = clock.now();
+							dimmed     = None;
+							warned     = None;
 							correction = 0;
 						}
 
 						Request::Reset(Event::Blank) | Request::Unblanked => {
 							blanked   = None;
-							unblanked = Some(Instant::now());
+							unblanked = Some(clock.now());
+							powered   = None;
 						}
 
 						Request::Suspend(time) => {
@@ -216,86 +436,46 @@ impl Timer {
 						}
 
 						Request::Resume => {
-							correction += suspended.take().unwrap().elapsed().unwrap_or(Duration::from_secs(0)).as_secs();
+							correction += clock.system().duration_since(suspended.take().unwrap()).unwrap_or(Duration::from_secs(0)).as_secs();
 							corrected   = false;
 						}
 
 						Request::Blanked => {
-							blanked = Some(Instant::now());
+							blanked = Some(clock.now());
 						}
 
 						Request::Started => {
-							started = Some(Instant::now());
+							started = Some(clock.now());
 						}
 
 						Request::Locked => {
-							locked = Some(Instant::now());
+							locked = Some(clock.now());
 						}
 
 						Request::Stopped => {
-							idle       = Instant::now();
+							idle       = clock.now();
 							started    = None;
 							locked     = None;
+							dimmed     = None;
 							blanked    = None;
+							powered    = None;
+							warned     = None;
 							correction = 0;
 						}
-					}
-				}
 
-				// Handle custom timeouts.
-				{
-					let mut expired = HashSet::new();
+						// The timers are checked again with the new values once the
+						// pending requests are handled.
+						Request::Refresh => (),
 
-					for (&id, &(ref started, seconds)) in &timeouts {
-						if started.elapsed().as_secs() > seconds {
-							expired.insert(id);
+						// Go back to checking the timers before handling any other
+						// request.
+						#[cfg(test)]
+						Request::Step => {
+							break;
 						}
 					}
 
-					for &id in &expired {
-						sender.send(Response::Timeout { id }).unwrap();
-						timeouts.remove(&id);
-					}
-				}
-
-				// If it's time to send a heart beat, send one and reset.
-				if beat.elapsed().as_secs() >= config.beat() as u64 {
-					beat = Instant::now();
-					sender.send(Response::Heartbeat(idle)).unwrap();
-				}
-
-				// Do not check events if the timers are suspended.
-				if suspended.is_some() {
-					continue;
-				}
-
-				// If blanking is enabled and the screen is not already blanked.
-				if let (Some(after), false) = (config.blank(), blanked.is_some()) {
-					if unblanked.unwrap_or(idle).elapsed().as_secs() >= after as u64 {
-						sender.send(Response::Blank).unwrap();
-						blanked = Some(Instant::now());
-					}
-				}
-
-				// If the system has been idle long enough send the message.
-				if started.is_none() && idle.elapsed().as_secs() + correction >= config.timeout() as u64 {
-					sender.send(Response::Start).unwrap();
-					started = Some(Instant::now());
-				}
-
-				// If the screen saver has been started, the screen is not locked and locking is enabled.
-				if let (Some(start), Some(after), false) = (started, config.lock(), locked.is_some()) {
-					if start.elapsed().as_secs() + correction >= after as u64 {
-						sender.send(Response::Lock).unwrap();
-						locked = Some(Instant::now());
-					}
-				}
-
-				// Only resume after one corrected loop, this avoids activities right
-				// after resume cancelling timer events.
-				if !corrected {
-					sender.send(Response::Resumed).unwrap();
-					corrected = true;
+					request = receiver.try_recv().ok();
 				}
 			}
 		});
@@ -306,60 +486,331 @@ impl Timer {
 		})
 	}
 
-	pub fn timeout(&self, value: Timeout) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Timeout(value))
+	/// Send a request to the timer.
+	pub fn send(&self, request: Request) -> Result<(), SendError<Request>> {
+		self.sender.send(request)
+	}
+
+	/// Check the timers again, to be used after advancing a manual clock.
+	#[cfg(test)]
+	pub fn step(&self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Step)
+	}
+}
+
+impl Deref for Timer {
+	type Target = Receiver<Response>;
+
+	fn deref(&self) -> &Receiver<Response> {
+		&self.receiver
+	}
+}
+
+/// The source of time for the timers.
+pub trait Clock: Send + Sync {
+	/// The current monotonic time.
+	fn now(&self) -> Instant;
+
+	/// The current wall-clock time.
+	fn system(&self) -> SystemTime;
+
+	/// How long to wait for requests before the given deadline, `None` waits
+	/// for requests only.
+	fn wait(&self, deadline: Instant) -> Option<Duration>;
+}
+
+/// The system clock.
+pub struct System;
+
+impl Clock for System {
+	fn now(&self) -> Instant {
+		Instant::now()
 	}
 
-	/// Request a report wiht the given id.
-	pub fn report(&self, id: u64) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Report { id: id })
+	fn system(&self) -> SystemTime {
+		SystemTime::now()
 	}
 
-	/// Reset the given timer.
-	pub fn reset(&self, event: Event) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Reset(event))
+	fn wait(&self, deadline: Instant) -> Option<Duration> {
+		Some(deadline.saturating_duration_since(Instant::now()))
 	}
+}
 
-	/// Request the timers to suspend at the given time.
-	pub fn suspend(&self, value: SystemTime) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Suspend(value))
+/// A clock that only moves when advanced.
+///
+/// The timers never wait on it, after advancing it `Timer::step` makes them
+/// handle whatever became due.
+#[cfg(test)]
+pub struct Manual(Mutex<(Instant, SystemTime)>);
+
+#[cfg(test)]
+impl Manual {
+	/// Create a clock starting at the current time.
+	pub fn new() -> Manual {
+		Manual(Mutex::new((Instant::now(), SystemTime::now())))
 	}
 
-	/// Request the timers to resume.
-	pub fn resume(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Resume)
+	/// Move the clock forward.
+	pub fn advance(&self, by: Duration) {
+		let mut time = self.0.lock().unwrap();
+		time.0 += by;
+		time.1 += by;
 	}
 
-	/// Notice the screen has been blanked.
-	pub fn blanked(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Blanked)
+	/// Move only the wall-clock time forward, like while the system is
+	/// suspended.
+	pub fn suspend(&self, by: Duration) {
+		self.0.lock().unwrap().1 += by;
 	}
+}
 
-	/// Notice the screen has been unblanked.
-	pub fn unblanked(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Unblanked)
+#[cfg(test)]
+impl Default for Manual {
+	fn default() -> Manual {
+		Manual::new()
 	}
+}
 
-	/// Notice the screen saver has started.
-	pub fn started(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Started)
+#[cfg(test)]
+impl Clock for Manual {
+	fn now(&self) -> Instant {
+		self.0.lock().unwrap().0
 	}
 
-	/// Notice the screen has been locked.
-	pub fn locked(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Locked)
+	fn system(&self) -> SystemTime {
+		self.0.lock().unwrap().1
 	}
 
-	/// Notice the screen saver was stopped.
-	pub fn stopped(&self) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Stopped)
+	fn wait(&self, _deadline: Instant) -> Option<Duration> {
+		None
 	}
 }
 
-impl Deref for Timer {
-	type Target = Receiver<Response>;
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::time::Duration;
 
-	fn deref(&self) -> &Receiver<Response> {
-		&self.receiver
+	use toml;
+
+	use crate::config;
+	use super::*;
+
+	/// Spawn a timer on a manual clock with the given configuration.
+	fn timer(source: &str) -> (Timer, Arc<Manual>) {
+		let table = source.parse::<toml::Value>().unwrap();
+		let table = table.as_table().unwrap();
+
+		let config = config::Timer::default();
+		config.load(table);
+
+		let power = config::Power::default();
+		power.load(table);
+
+		let clock = Arc::new(Manual::new());
+		let timer = Timer::with_clock(config, power, clock.clone()).unwrap();
+
+		// The first check always reports the timers as resumed.
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Resumed]));
+
+		(timer, clock)
+	}
+
+	/// Advance the clock and collect what the timers reported.
+	fn step(timer: &Timer, clock: &Manual, seconds: u64) -> Vec<Response> {
+		// Requests sent before the step have to be handled before the clock
+		// moves.
+		let mut responses = flush(timer);
+
+		clock.advance(Duration::from_secs(seconds));
+		timer.step().unwrap();
+		responses.extend(flush(timer));
+
+		responses
+	}
+
+	/// Wait for the pending requests to be handled, collecting what the timers
+	/// reported in the meantime.
+	fn flush(timer: &Timer) -> Vec<Response> {
+		// Reports are answered in order, so anything before it comes from the
+		// previous requests.
+		timer.sender.send(Request::Report { id: 0 }).unwrap();
+
+		let mut responses = Vec::new();
+
+		loop {
+			match timer.recv_timeout(Duration::from_secs(5)).unwrap() {
+				Response::Report { .. } =>
+					return responses,
+
+				response =>
+					responses.push(response),
+			}
+		}
+	}
+
+	#[test]
+	fn stages() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 3600
+			dim     = 10
+			blank   = 20
+			timeout = 30
+			lock    = 10
+		"#);
+
+		assert!(matches!(step(&timer, &clock, 5)[..], []));
+		assert!(matches!(step(&timer, &clock, 5)[..], [Response::Dim]));
+		assert!(matches!(step(&timer, &clock, 10)[..], [Response::Blank]));
+		assert!(matches!(step(&timer, &clock, 10)[..], [Response::Start]));
+		assert!(matches!(step(&timer, &clock, 9)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Lock]));
+		assert!(matches!(step(&timer, &clock, 3000)[..], []));
+	}
+
+	#[test]
+	fn power() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 3600
+			timeout = 3000
+
+			[power]
+			standby = 10
+			off     = 20
+		"#);
+
+		assert!(matches!(step(&timer, &clock, 10)[..], [Response::Power(Power::Standby)]));
+		assert!(matches!(step(&timer, &clock, 10)[..], [Response::Power(Power::Off)]));
+
+		timer.sender.send(Request::Unblanked).unwrap();
+		assert!(matches!(step(&timer, &clock, 10)[..], [Response::Power(Power::Standby)]));
+	}
+
+	#[test]
+	fn activity() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 3600
+			dim     = 10
+			timeout = 30
+		"#);
+
+		assert!(matches!(step(&timer, &clock, 20)[..], [Response::Dim]));
+
+		timer.sender.send(Request::Reset(Event::Idle)).unwrap();
+		assert!(matches!(step(&timer, &clock, 9)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Dim]));
+		assert!(matches!(step(&timer, &clock, 19)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Start]));
+
+		timer.sender.send(Request::Stopped).unwrap();
+		assert!(matches!(step(&timer, &clock, 29)[..], [Response::Dim]));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Start]));
+	}
+
+	#[test]
+	fn suspend() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 3600
+			timeout = 60
+		"#);
+
+		assert!(matches!(step(&timer, &clock, 20)[..], []));
+
+		timer.sender.send(Request::Suspend(clock.system())).unwrap();
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Suspended(_)]));
+
+		// Nothing fires while suspended.
+		assert!(matches!(step(&timer, &clock, 60)[..], []));
+
+		// The time spent suspended counts as idle time.
+		clock.suspend(Duration::from_secs(30));
+		timer.sender.send(Request::Resume).unwrap();
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Start, Response::Resumed]));
+	}
+
+	#[test]
+	fn correction() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 3600
+			timeout = 60
+		"#);
+
+		assert!(matches!(step(&timer, &clock, 20)[..], []));
+
+		timer.sender.send(Request::Suspend(clock.system())).unwrap();
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Suspended(_)]));
+
+		clock.suspend(Duration::from_secs(30));
+		timer.sender.send(Request::Resume).unwrap();
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Resumed]));
+		assert!(matches!(step(&timer, &clock, 9)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Start]));
+
+		// Restarting the timers drops the correction.
+		timer.sender.send(Request::Stopped).unwrap();
+		assert!(matches!(step(&timer, &clock, 59)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Start]));
+	}
+
+	#[test]
+	fn timeout() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 3600
+			timeout = 3000
+		"#);
+
+		timer.sender.send(Request::Timeout(Timeout::Set { id: 1, seconds: 5 })).unwrap();
+		timer.sender.send(Request::Timeout(Timeout::Set { id: 2, seconds: 10 })).unwrap();
+		timer.sender.send(Request::Timeout(Timeout::Set { id: 3, seconds: 5 })).unwrap();
+		timer.sender.send(Request::Timeout(Timeout::Cancel { id: 3 })).unwrap();
+
+		assert!(matches!(step(&timer, &clock, 4)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Timeout { id: 1 }]));
+		assert!(matches!(step(&timer, &clock, 5)[..], [Response::Timeout { id: 2 }]));
+		assert!(matches!(step(&timer, &clock, 10)[..], []));
+	}
+
+	#[test]
+	fn heartbeat() {
+		let (timer, clock) = timer(r#"
+			[timer]
+			beat    = 10
+			timeout = 3000
+		"#);
+
+		assert!(matches!(step(&timer, &clock, 9)[..], []));
+		assert!(matches!(step(&timer, &clock, 1)[..], [Response::Heartbeat(_)]));
+		assert!(matches!(step(&timer, &clock, 10)[..], [Response::Heartbeat(_)]));
+	}
+
+	#[test]
+	fn condition() {
+		let table = r#"
+			[timer]
+			beat    = 3600
+			timeout = 60
+
+			[timer.battery]
+			timeout = 10
+		"#.parse::<toml::Value>().unwrap();
+
+		let config = config::Timer::default();
+		config.load(table.as_table().unwrap());
+
+		let clock = Arc::new(Manual::new());
+		let timer = Timer::with_clock(config.clone(), config::Power::default(), clock.clone()).unwrap();
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Resumed]));
+		assert!(matches!(step(&timer, &clock, 20)[..], []));
+
+		// The new timeout applies as soon as the condition changes.
+		config.condition("battery", true);
+		timer.sender.send(Request::Refresh).unwrap();
+		assert!(matches!(step(&timer, &clock, 0)[..], [Response::Start]));
 	}
 }